@@ -0,0 +1,200 @@
+//! Analysis of the chords of a song.
+//!
+//! `Song::detect_key` guesses the key of songs without a `{key}`:
+//! ```
+//! # use chordpro::Song;
+//! # use std::str::FromStr;
+//! let song = Song::from_str("[Am]Walking [F]down the [G]road [E7]alone, [Am]home").unwrap();
+//! let candidates = song.detect_key();
+//!
+//! assert_eq!(candidates[0].key.to_string(), "Am");
+//! ```
+use crate::chords::{interval, Chord, ChordQuality, Note};
+use crate::key::{Key, Mode};
+use crate::song::{Chunk, Section, Song};
+
+/// A possible key of a song
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyCandidate {
+    pub key: Key,
+    /// How well the chords of the song fit the key, between 0 and 1
+    pub confidence: f32,
+}
+
+/// Chords that belong to each mode, as semitones from the tonic to their
+/// root and their quality. The tonic chord goes first.
+const MAJOR_CHORDS: &[(u8, ChordQuality)] = {
+    use ChordQuality::*;
+    &[(0, Major), (2, Minor), (4, Minor), (5, Major), (7, Major), (9, Minor), (11, Diminished)]
+};
+const MINOR_CHORDS: &[(u8, ChordQuality)] = {
+    use ChordQuality::*;
+    // Both the minor and the major (harmonic) dominant
+    &[(0, Minor), (2, Diminished), (3, Major), (5, Minor), (7, Minor), (7, Major), (8, Major), (10, Major)]
+};
+
+/// How much each part of the analysis weighs in the confidence
+const CHORDS_WEIGHT: f32 = 0.7;
+const FIRST_CHORD_WEIGHT: f32 = 0.15;
+const LAST_CHORD_WEIGHT: f32 = 0.15;
+
+/// Quality as it is used in a key: a half diminished chord is diminished,
+/// and suspended and power chords have no third, so they fit any
+fn key_quality(quality: ChordQuality) -> Option<ChordQuality> {
+    match quality {
+        ChordQuality::HalfDiminished => Some(ChordQuality::Diminished),
+        ChordQuality::Suspended2 | ChordQuality::Suspended4 | ChordQuality::Power => None,
+        quality => Some(quality),
+    }
+}
+
+impl Key {
+    fn chords(&self) -> &'static [(u8, ChordQuality)] {
+        match self.mode {
+            Mode::Major => MAJOR_CHORDS,
+            Mode::Minor => MINOR_CHORDS,
+        }
+    }
+
+    /// Whether `chord` is the tonic chord of the key
+    fn is_tonic(&self, chord: &Chord) -> bool {
+        let (_, quality) = self.chords()[0];
+        chord.root.pitch_class() == self.tonic.pitch_class()
+            && key_quality(chord.quality).map_or(true, |q| q == quality)
+    }
+
+    /// How well `chord` fits the key: 1 for the tonic chord, less for the
+    /// other chords of the key and even less for the ones that only have
+    /// their root in the scale
+    fn fit(&self, chord: &Chord) -> f32 {
+        let degree = interval(self.tonic.pitch_class(), chord.root.pitch_class());
+        let quality = key_quality(chord.quality);
+        let mut chords = self.chords().iter().filter(|(d, _)| *d == degree).peekable();
+
+        if self.is_tonic(chord) {
+            1.0
+        } else if chords.clone().any(|(_, q)| quality.map_or(true, |quality| quality == *q)) {
+            0.8
+        } else if chords.peek().is_some() {
+            0.4
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Chords of the song with how long they last: the lyrics sung over
+/// them, or a beat in grids
+fn timed_chords(song: &Song) -> Vec<(&Chord, f32)> {
+    let mut chords = Vec::new();
+
+    for section in song.iter() {
+        let mut inner = section;
+        while let Section::Conditional(_, s) = inner {
+            inner = s;
+        }
+        if let Section::Grid(grid) = inner {
+            chords.extend(grid.chords().map(|chord| (chord, 1.0)));
+        }
+
+        for line in section.iter() {
+            let mut last: Option<(&Chord, f32)> = None;
+            for chunk in line.iter() {
+                match chunk {
+                    Chunk::Chord(chord) => chords.extend(last.replace((chord, 1.0))),
+                    Chunk::Lyrics(lyrics) => {
+                        if let Some((_, duration)) = last.as_mut() {
+                            *duration += lyrics.trim().chars().count() as f32 / 10.0;
+                        }
+                    }
+                }
+            }
+            chords.extend(last);
+        }
+    }
+
+    chords
+}
+
+impl Song {
+    /// Guesses the key of the song from its chords, ignoring its `{key}`.
+    ///
+    /// Every major and minor key is returned, with the most likely first.
+    /// The chords that fit a key make it more likely, weighted by how long
+    /// they last, and so do first and last chords that are its tonic. A
+    /// song without chords gives an empty list.
+    pub fn detect_key(&self) -> Vec<KeyCandidate> {
+        let chords = timed_chords(self);
+        let (first, last) = match (chords.first(), chords.last()) {
+            (Some((first, _)), Some((last, _))) => (*first, *last),
+            _ => return Vec::new(),
+        };
+        let duration: f32 = chords.iter().map(|(_, d)| d).sum();
+
+        let mut candidates: Vec<KeyCandidate> = [Key::major(Note::C), Key::minor(Note::A)]
+            .iter()
+            .flat_map(|key| (0..12).map(move |s| key.transpose(s)))
+            .map(|key| {
+                let fit: f32 = chords.iter().map(|(chord, d)| key.fit(chord) * d).sum();
+                let mut confidence = CHORDS_WEIGHT * fit / duration;
+                if key.is_tonic(first) {
+                    confidence += FIRST_CHORD_WEIGHT;
+                }
+                if key.is_tonic(last) {
+                    confidence += LAST_CHORD_WEIGHT;
+                }
+
+                KeyCandidate { key, confidence }
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn detect(song: &str) -> Vec<String> {
+        let song = Song::from_str(song).expect("Failed to parse song");
+        song.detect_key().iter().map(|c| c.key.to_string()).collect()
+    }
+
+    #[test]
+    fn detect_key() {
+        assert_eq!(detect("[G]One [C]two [D7]three [G]four")[0], "G");
+        assert_eq!(detect("[Em]One [C]two [D]three [B7]four [Em]five")[0], "Em");
+        assert_eq!(detect("[Eb]One [Ab]two [Bb7]three [Eb]four")[0], "Eb");
+        assert_eq!(detect("[F#m]One [D]two [E]three [C#7]four [F#m]five")[0], "F#m");
+        assert_eq!(detect("{sog}\n| Dm . Gm . | A7 . Dm . |\n{eog}")[0], "Dm");
+
+        // The same chords, in C or in A minor
+        assert_eq!(detect("[C]One [Am]two [F]three [G]four [C]five")[0], "C");
+        assert_eq!(detect("[Am]One [C]two [F]three [G]four [Am]five")[0], "Am");
+
+        assert!(detect("No chords").is_empty());
+    }
+
+    #[test]
+    fn detect_key_duration() {
+        // The long chords are the ones of D major
+        let song = "[D]Here the chords last [G]long, [A]while the others [C]a [F]b";
+        assert_eq!(detect(song)[0], "D");
+    }
+
+    #[test]
+    fn confidence() {
+        let song = Song::from_str("[C]One [F]two [G]three [C]four").expect("Failed to parse song");
+        let candidates = song.detect_key();
+
+        assert_eq!(candidates.len(), 24);
+        assert_eq!(candidates[0].key, Key::major(Note::C));
+        assert!(candidates[0].confidence <= 1.0);
+        assert!(candidates[0].confidence > candidates[1].confidence);
+        assert!(candidates.windows(2).all(|c| c[0].confidence >= c[1].confidence));
+        assert!(candidates.iter().all(|c| c.confidence >= 0.0));
+    }
+}
@@ -0,0 +1,171 @@
+//! Capo suggestions for guitarists.
+//!
+//! `Song::suggest_capo` tries every capo position and counts how many chords
+//! of the song can then be played with open shapes. A song in `Eb`, for
+//! example, is easier on capo 1 with `D` shapes:
+//! ```
+//! # use chordpro::Song;
+//! # use std::str::FromStr;
+//! let song = Song::from_str("{key: Eb}\n[Eb]One [Ab]two [Bb]three").unwrap();
+//! let best = &song.suggest_capo()[0];
+//!
+//! assert_eq!(best.capo, 1);
+//! assert_eq!(best.key.unwrap().to_string(), "D");
+//! ```
+use crate::chords::{Chord, Note};
+use crate::key::Key;
+use crate::song::Song;
+use crate::transpose::{Pitch, Transposer};
+use std::str::FromStr;
+
+/// Highest capo position suggested
+const MAX_CAPO: u8 = 7;
+
+/// Chords played with open strings on the guitar
+const OPEN_SHAPES: &[&str] = &[
+    "C", "D", "E", "G", "A", "Am", "Dm", "Em", "A7", "B7", "C7", "D7", "E7", "G7", "Am7", "Dm7",
+    "Em7", "Amaj7", "Cmaj7", "Dmaj7", "Emaj7", "Fmaj7", "Asus2", "Asus4", "Dsus2", "Dsus4",
+    "Esus4", "Cadd9", "A5", "D5", "E5",
+];
+
+/// A capo position for a song
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapoSuggestion {
+    /// Fret of the capo, 0 for no capo
+    pub capo: u8,
+    /// Key of the shapes played with the capo, when the key of the song is
+    /// known
+    pub key: Option<Key>,
+    /// The song as played with the capo
+    pub song: Song,
+    /// How many chords of the song have an open shape with the capo,
+    /// counting repetitions
+    pub open: usize,
+    /// How many chords the song has, counting repetitions
+    pub total: usize,
+}
+
+impl CapoSuggestion {
+    /// Ratio of chords with an open shape, between 0 and 1
+    pub fn score(&self) -> f32 {
+        match self.total {
+            0 => 0.0,
+            total => self.open as f32 / total as f32,
+        }
+    }
+}
+
+/// Whether `chord` is played with one of `shapes`. The bass of slash
+/// chords is not taken into account.
+fn is_open(chord: &Chord, shapes: &[(Note, Vec<u8>)]) -> bool {
+    let chord = Chord {
+        bass: chord.root,
+        ..chord.clone()
+    };
+    let shape = (chord.root.pitch_class(), chord.intervals());
+
+    shapes.contains(&shape)
+}
+
+impl Song {
+    /// Suggests capo positions for the song, the best first: the ones where
+    /// more chords have an open shape, and the lowest capo among them.
+    ///
+    /// The song is taken at concert pitch, so its own `{capo}` is applied
+    /// first, modulo an octave. The key of the shapes comes from the `{key}`
+    /// of the song, or `Song::detect_key`.
+    pub fn suggest_capo(&self) -> Vec<CapoSuggestion> {
+        let shapes: Vec<(Note, Vec<u8>)> = OPEN_SHAPES
+            .iter()
+            .filter_map(|shape| Chord::from_str(shape).ok())
+            .map(|chord| (chord.root.pitch_class(), chord.intervals()))
+            .collect();
+
+        let mut concert = self.transposed(Pitch::Concert);
+        let key = concert
+            .key()
+            .or_else(|| concert.detect_key().first().map(|candidate| candidate.key));
+        if let Some(key) = key {
+            concert.set_key(key);
+        }
+
+        let mut suggestions: Vec<CapoSuggestion> = (0..=MAX_CAPO)
+            .map(|capo| {
+                let semitones = -(capo as i8);
+                let key = key.map(|key| key.transpose(semitones));
+                let transposer = match key {
+                    Some(key) => Transposer::new(semitones).in_key(key),
+                    None => Transposer::new(semitones),
+                };
+
+                let mut song = transposer.transpose(concert.clone());
+                song.metadata.capo = capo;
+
+                let total = song.chords().count();
+                let open = song.chords().filter(|c| is_open(c, &shapes)).count();
+
+                CapoSuggestion {
+                    capo,
+                    key,
+                    song,
+                    open,
+                    total,
+                }
+            })
+            .collect();
+
+        suggestions.sort_by(|a, b| b.open.cmp(&a.open).then(a.capo.cmp(&b.capo)));
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chords(song: &Song) -> Vec<String> {
+        song.chords().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn suggest_capo() {
+        let song = Song::from_str("{key: Eb}\n[Eb]One [Ab]two [Bb]three [Cm]four [Bb/D]five")
+            .expect("Failed to parse song");
+        let suggestions = song.suggest_capo();
+
+        assert_eq!(suggestions.len(), MAX_CAPO as usize + 1);
+        assert_eq!(suggestions[0].capo, 1);
+        assert_eq!(suggestions[0].key, Some(Key::from_str("D").unwrap()));
+        assert_eq!(chords(&suggestions[0].song), vec!["D", "G", "A", "Bm", "A/C#"]);
+        assert_eq!((suggestions[0].open, suggestions[0].total), (4, 5));
+        assert_eq!(suggestions[0].song.metadata.capo, 1);
+        assert_eq!(suggestions[0].song.metadata.key.as_deref(), Some("D"));
+
+        let no_capo = suggestions.iter().find(|s| s.capo == 0).unwrap();
+        assert_eq!(no_capo.open, 0);
+        assert_eq!(no_capo.score(), 0.0);
+    }
+
+    #[test]
+    fn suggest_capo_from_capo() {
+        // Already played on capo 3 with G shapes: the song is in Bb
+        let song = Song::from_str("{capo: 3}\n[G]One [Em]two [C]three [D]four").expect("Failed to parse song");
+        let suggestions = song.suggest_capo();
+
+        assert_eq!(suggestions[0].capo, 3);
+        assert_eq!(suggestions[0].key, Some(Key::from_str("G").unwrap()));
+        assert_eq!(suggestions[0].score(), 1.0);
+        assert_eq!(chords(&suggestions[0].song), vec!["G", "Em", "C", "D"]);
+    }
+
+    #[test]
+    fn suggest_capo_out_of_range() {
+        // A capo of 200 frets sounds 8 semitones higher: the song is in Ab
+        let song = Song::from_str("{capo: 200}\n[C]One [F]two [G]three").expect("Failed to parse song");
+        let suggestions = song.suggest_capo();
+
+        assert_eq!(suggestions.len(), MAX_CAPO as usize + 1);
+        assert_eq!(suggestions[0].capo, 1);
+        assert_eq!(chords(&suggestions[0].song), vec!["G", "C", "D"]);
+    }
+}
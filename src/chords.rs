@@ -1,8 +1,10 @@
 use num_traits::{FromPrimitive, ToPrimitive};
 use serde::Serialize;
+use std::hash::{Hash, Hasher};
 
-#[derive(Copy, Serialize, Debug, PartialEq, Clone, FromPrimitive, ToPrimitive)]
+#[derive(Copy, Serialize, Debug, Default, PartialEq, Eq, Hash, Clone, FromPrimitive, ToPrimitive)]
 pub enum Note {
+    #[default]
     A,
     ASharp,
     B,
@@ -17,55 +19,638 @@ pub enum Note {
     GSharp,
 }
 
-#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+/// Letter name of a note
+#[derive(Copy, Serialize, Debug, Default, PartialEq, Eq, Hash, Clone, FromPrimitive, ToPrimitive)]
+pub enum Letter {
+    C,
+    D,
+    E,
+    F,
+    G,
+    #[default]
+    A,
+    B,
+}
+
+impl Letter {
+    /// Note of the letter without accidentals
+    pub fn natural(&self) -> Note {
+        match self {
+            Letter::C => Note::C,
+            Letter::D => Note::D,
+            Letter::E => Note::E,
+            Letter::F => Note::F,
+            Letter::G => Note::G,
+            Letter::A => Note::A,
+            Letter::B => Note::B,
+        }
+    }
+
+    /// Letter `steps` letters above this one, wrapping from B to C
+    pub fn step(&self, steps: i8) -> Letter {
+        Letter::from_i8((self.to_i8().unwrap() + steps).rem_euclid(7)).unwrap()
+    }
+}
+
+/// Accidental of a spelled note
+#[derive(Copy, Serialize, Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub enum Accidental {
+    /// `bb`
+    DoubleFlat,
+    /// `b`
+    Flat,
+    #[default]
+    Natural,
+    /// `#`
+    Sharp,
+    /// `##`
+    DoubleSharp,
+}
+
+impl Accidental {
+    /// Semitones the accidental raises the note
+    pub fn semitones(&self) -> i8 {
+        match self {
+            Accidental::DoubleFlat => -2,
+            Accidental::Flat => -1,
+            Accidental::Natural => 0,
+            Accidental::Sharp => 1,
+            Accidental::DoubleSharp => 2,
+        }
+    }
+
+    /// Accidental that raises the note `semitones`, from -2 to 2
+    pub fn from_semitones(semitones: i8) -> Option<Accidental> {
+        match semitones {
+            -2 => Some(Accidental::DoubleFlat),
+            -1 => Some(Accidental::Flat),
+            0 => Some(Accidental::Natural),
+            1 => Some(Accidental::Sharp),
+            2 => Some(Accidental::DoubleSharp),
+            _ => None,
+        }
+    }
+}
+
+/// A note as it is written: a letter and an accidental, so that `Db` and
+/// `C#` are different even if they are the same `Note`.
+#[derive(Copy, Serialize, Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub struct SpelledNote {
+    pub letter: Letter,
+    pub accidental: Accidental,
+}
+
+impl SpelledNote {
+    pub fn new(letter: Letter, accidental: Accidental) -> Self {
+        SpelledNote { letter, accidental }
+    }
+
+    /// The note it sounds as
+    pub fn pitch_class(&self) -> Note {
+        self.letter.natural() + self.accidental.semitones()
+    }
+
+    /// Spells `note` with `letter`, if it needs at most two accidentals
+    pub fn with_letter(note: Note, letter: Letter) -> Option<Self> {
+        let semitones = (interval(letter.natural(), note) as i8 + 6).rem_euclid(12) - 6;
+        Accidental::from_semitones(semitones).map(|accidental| SpelledNote::new(letter, accidental))
+    }
+
+    /// Spells `note` without accidentals, or with a sharp: `C#`, `D#`...
+    pub fn sharp(note: Note) -> Self {
+        SpelledNote::with_letter(note, natural_letter(note).unwrap_or_else(|| natural_letter(note - 1).unwrap()))
+            .unwrap()
+    }
+
+    /// Spells `note` without accidentals, or with a flat: `Db`, `Eb`...
+    pub fn flat(note: Note) -> Self {
+        SpelledNote::with_letter(note, natural_letter(note).unwrap_or_else(|| natural_letter(note + 1).unwrap()))
+            .unwrap()
+    }
+}
+
+/// Letter of `note` if it has no accidentals
+fn natural_letter(note: Note) -> Option<Letter> {
+    (0..7)
+        .map(|i| Letter::from_i8(i).unwrap())
+        .find(|l| l.natural() == note)
+}
+
+/// The usual spelling of each note: with sharps, except `Bb`
+impl From<Note> for SpelledNote {
+    fn from(note: Note) -> Self {
+        match note {
+            Note::ASharp => SpelledNote::flat(note),
+            _ => SpelledNote::sharp(note),
+        }
+    }
+}
+
+/// Names of the notes of the chords in a song
+#[derive(Copy, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+pub enum NoteNames {
+    /// Letter or latin names, as the first chord of the song. Chords with
+    /// the other names are still read, with an `ErrorKind::MixedNoteNames`
+    /// warning.
+    #[default]
+    Auto,
+    /// Letter names: `C`, `Bb`, `F#m`
+    Letters,
+    /// Latin (solfège) names: `Do` (or `Ut`), `Sib`, `Fa#m`
+    Latin,
+    /// German names, where `H` is B and `B` is B flat: `H7`, `B`, `F#m`.
+    /// They are never detected, as `B` would be read as B.
+    German,
+}
+
+/// Alteration of a chord degree
+#[derive(Copy, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub enum Alteration {
+    /// `b`
+    Flat,
+    /// `#`
+    Sharp,
+}
+
+/// Quality of a chord, given by its triad
+#[derive(Copy, Serialize, Debug, Default, PartialEq, Eq, Hash, Clone)]
+#[non_exhaustive]
+pub enum ChordQuality {
+    /// `C`, `Cmaj`
+    #[default]
+    Major,
+    /// `Cm`, `Cmin`, `C-`
+    Minor,
+    /// `Caug`, `C+`
+    Augmented,
+    /// `Cdim`, `C°`
+    Diminished,
+    /// `Cø`, `Cm7b5`: a diminished triad with a minor seventh
+    HalfDiminished,
+    /// `Csus2`
+    Suspended2,
+    /// `Csus4`, `Csus`
+    Suspended4,
+    /// `C5`: root and fifth, without third
+    Power,
+}
+
+/// Extension or alteration of a chord, like the `7`, `b9` and `#11` of `G7b9#11`
+#[derive(Copy, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[serde(tag = "class", content = "content")]
+#[non_exhaustive]
+pub enum Extension {
+    /// A degree, that may be altered: `7`, `13`, `b9`, `#11`. Degrees
+    /// from the seventh up have a minor seventh.
+    Degree(Option<Alteration>, u8),
+    /// A degree with a major seventh: `maj7`, `M9`, `Δ7`
+    Major(u8),
+    /// A degree added to the triad: `add9`, `add#11`
+    Add(Option<Alteration>, u8),
+    /// A degree left out: `omit3`, `no5`
+    Omit(u8),
+}
+
+/// A chord.
+///
+/// Chords are compared by their canonical form (see `Chord::normalized`),
+/// so `Cmaj7`, `CM7` and `CΔ7` are equal, and so are `Cm7b5` and `Cø`.
+#[derive(Serialize, Debug, Default, Clone)]
 pub struct Chord {
-    pub root: Note,
-    pub minor: bool,
-    pub others: String,
-    pub number: u8,
-    pub bass: Note,
+    pub root: SpelledNote,
+    pub quality: ChordQuality,
+    /// Extensions and alterations, in the order they are written
+    pub extensions: Vec<Extension>,
+    pub bass: SpelledNote,
 }
 
 impl Chord {
-    pub fn major(n: Note) -> Self {
+    pub fn major(n: impl Into<SpelledNote>) -> Self {
+        let n = n.into();
         Chord {
             root: n,
-            minor: false,
-            others: String::new(),
-            number: 0,
+            quality: ChordQuality::Major,
+            extensions: Vec::new(),
             bass: n,
         }
     }
 
-    pub fn minor(n: Note) -> Self {
+    pub fn minor(n: impl Into<SpelledNote>) -> Self {
         Chord {
-            root: n,
-            minor: true,
-            others: String::new(),
-            number: 0,
-            bass: n,
+            quality: ChordQuality::Minor,
+            ..Chord::major(n)
+        }
+    }
+
+    /// The same chord, written in its canonical way: a minor chord with a
+    /// seventh and a flat fifth is half-diminished, `C5` is a power chord, and
+    /// degrees implied by others (the `7` of `C7(13)`) are removed.
+    pub fn normalized(&self) -> Chord {
+        let mut chord = self.clone();
+        let seventh = Extension::Degree(None, 7);
+        let flat_fifth = Extension::Degree(Some(Alteration::Flat), 5);
+
+        let upper = chord
+            .extensions
+            .iter()
+            .any(|e| matches!(e, Extension::Degree(None, 9 | 11 | 13)));
+        let has_seventh = upper || chord.extensions.contains(&seventh);
+        if upper {
+            chord.extensions.retain(|e| *e != seventh);
+        }
+
+        match chord.quality {
+            ChordQuality::Minor if has_seventh && chord.extensions.contains(&flat_fifth) => {
+                chord.quality = ChordQuality::HalfDiminished;
+                chord.extensions.retain(|e| *e != seventh && *e != flat_fifth);
+            }
+            ChordQuality::HalfDiminished => {
+                chord.extensions.retain(|e| *e != seventh && *e != flat_fifth)
+            }
+            ChordQuality::Major if chord.extensions == [Extension::Degree(None, 5)] => {
+                chord.quality = ChordQuality::Power;
+                chord.extensions.clear();
+            }
+            _ => {}
+        }
+
+        chord
+    }
+
+    /// Pitch classes of the chord, as semitones from the root (from 0 to 11),
+    /// in ascending order. Includes the extensions, alterations and the bass.
+    ///
+    /// A plain `7` (and the sevenths implied by `9`, `11` and `13`) is a
+    /// minor seventh, or a diminished one in diminished chords.
+    pub fn intervals(&self) -> Vec<u8> {
+        let mut set = [false; 12];
+        for &i in self.quality.triad() {
+            set[i as usize] = true;
+        }
+
+        let add = |set: &mut [bool; 12], degree: u8, alteration: Option<Alteration>| {
+            let semitones = degree_semitones(degree) as i8 + alteration_semitones(alteration);
+            set[semitones.rem_euclid(12) as usize] = true;
+        };
+        let seventh = match self.quality {
+            ChordQuality::Diminished => 9,
+            _ => 10,
+        };
+
+        for extension in self.extensions.iter() {
+            match *extension {
+                Extension::Degree(None, 7) => set[seventh] = true,
+                Extension::Degree(None, n @ (9 | 11 | 13)) => {
+                    set[seventh] = true;
+                    stacked(n).for_each(|d| add(&mut set, d, None));
+                }
+                Extension::Major(n) => {
+                    set[11] = true;
+                    stacked(n).for_each(|d| add(&mut set, d, None));
+                }
+                Extension::Degree(Some(alteration), 5) => {
+                    set[7] = false;
+                    add(&mut set, 5, Some(alteration));
+                }
+                Extension::Degree(alteration, n) | Extension::Add(alteration, n) => add(&mut set, n, alteration),
+                Extension::Omit(_) => {}
+            }
+        }
+
+        for extension in self.extensions.iter() {
+            match *extension {
+                Extension::Omit(3) => [3, 4].iter().for_each(|&i| set[i] = false),
+                Extension::Omit(5) => [6, 7, 8].iter().for_each(|&i| set[i] = false),
+                Extension::Omit(n) => set[degree_semitones(n) as usize] = false,
+                _ => {}
+            }
+        }
+
+        set[interval(self.root.pitch_class(), self.bass.pitch_class()) as usize] = true;
+
+        (0..12).filter(|&i| set[i as usize]).collect()
+    }
+
+    /// Notes of the chord: the bass first, if it is not the root, and then
+    /// the notes given by `Chord::intervals`, from the root up.
+    pub fn notes(&self) -> Vec<Note> {
+        let mut notes = Vec::new();
+        if self.bass != self.root {
+            notes.push(self.bass.pitch_class());
+        }
+
+        for i in self.intervals() {
+            let note = self.root.pitch_class() + i as i8;
+            if !notes.contains(&note) {
+                notes.push(note);
+            }
+        }
+
+        notes
+    }
+
+    /// Chords made of `notes`, from the most to the least likely. The first
+    /// note is taken as the lowest one: if it is not the root, the chord is
+    /// an inversion, written as a slash chord like `C/E`.
+    ///
+    /// Chords in root position go before inversions, and chords with all their
+    /// notes before those with the fifth left out (`C7(omit5)`).
+    pub fn identify(notes: &[Note]) -> Vec<Chord> {
+        let bass = match notes.first() {
+            Some(&bass) => bass,
+            None => return Vec::new(),
+        };
+
+        let mut pitches: Vec<Note> = Vec::new();
+        for &note in notes {
+            if !pitches.contains(&note) {
+                pitches.push(note);
+            }
         }
+
+        let mut candidates = Vec::new();
+        for &root in pitches.iter() {
+            let mut set: Vec<u8> = pitches.iter().map(|&n| interval(root, n)).collect();
+            set.sort_unstable();
+
+            for (rank, &(quality, extensions)) in TEMPLATES.iter().enumerate() {
+                let mut chord = Chord {
+                    root: root.into(),
+                    quality,
+                    extensions: extensions.to_vec(),
+                    bass: bass.into(),
+                };
+
+                let mut intervals = Chord { bass: root.into(), ..chord.clone() }.intervals();
+                let omitted = intervals != set;
+                if omitted {
+                    // The fifth is often left out, but a chord needs at least three notes
+                    intervals.retain(|&i| i != 7);
+                    if intervals != set || set.len() < 3 {
+                        continue;
+                    }
+                    chord.extensions.push(Extension::Omit(5));
+                }
+
+                candidates.push(((omitted, root != bass, rank), chord));
+            }
+        }
+
+        candidates.sort_by_key(|(key, _)| *key);
+        candidates.into_iter().map(|(_, chord)| chord).collect()
+    }
+
+    /// Canonical form, with the extensions in a fixed order
+    fn canonical(&self) -> (SpelledNote, ChordQuality, Vec<Extension>, SpelledNote) {
+        let chord = self.normalized();
+        let mut extensions = chord.extensions;
+        extensions.sort();
+        extensions.dedup();
+
+        (chord.root, chord.quality, extensions, chord.bass)
+    }
+}
+
+/// Chords tried by `Chord::identify`, from the most to the least common
+const TEMPLATES: &[(ChordQuality, &[Extension])] = {
+    use Alteration::*;
+    use ChordQuality::*;
+    use Extension::{Add, Degree};
+
+    &[
+        (Major, &[]),
+        (Minor, &[]),
+        (Major, &[Degree(None, 7)]),
+        (Minor, &[Degree(None, 7)]),
+        (Major, &[Extension::Major(7)]),
+        (Suspended4, &[]),
+        (Suspended2, &[]),
+        (Diminished, &[]),
+        (Augmented, &[]),
+        (Power, &[]),
+        (Major, &[Degree(None, 6)]),
+        (Minor, &[Degree(None, 6)]),
+        (Suspended4, &[Degree(None, 7)]),
+        (HalfDiminished, &[]),
+        (Diminished, &[Degree(None, 7)]),
+        (Minor, &[Extension::Major(7)]),
+        (Major, &[Add(None, 9)]),
+        (Minor, &[Add(None, 9)]),
+        (Major, &[Degree(None, 9)]),
+        (Minor, &[Degree(None, 9)]),
+        (Major, &[Extension::Major(9)]),
+        (Augmented, &[Degree(None, 7)]),
+        (Major, &[Degree(None, 7), Degree(Some(Flat), 9)]),
+        (Major, &[Degree(None, 7), Degree(Some(Sharp), 9)]),
+        (Major, &[Degree(None, 11)]),
+        (Minor, &[Degree(None, 11)]),
+        (Major, &[Degree(None, 13)]),
+        (Minor, &[Degree(None, 13)]),
+    ]
+};
+
+impl ChordQuality {
+    /// Semitones from the root of the notes that make this quality
+    fn triad(&self) -> &'static [u8] {
+        match self {
+            ChordQuality::Major => &[0, 4, 7],
+            ChordQuality::Minor => &[0, 3, 7],
+            ChordQuality::Augmented => &[0, 4, 8],
+            ChordQuality::Diminished => &[0, 3, 6],
+            ChordQuality::HalfDiminished => &[0, 3, 6, 10],
+            ChordQuality::Suspended2 => &[0, 2, 7],
+            ChordQuality::Suspended4 => &[0, 5, 7],
+            ChordQuality::Power => &[0, 7],
+        }
+    }
+}
+
+/// Semitones from the root to a degree of the major scale, within an octave
+fn degree_semitones(degree: u8) -> u8 {
+    const SCALE: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+    SCALE[(degree.max(1) as usize - 1) % 7]
+}
+
+fn alteration_semitones(alteration: Option<Alteration>) -> i8 {
+    match alteration {
+        Some(Alteration::Flat) => -1,
+        Some(Alteration::Sharp) => 1,
+        None => 0,
+    }
+}
+
+/// Degrees above the seventh implied by an extension: a `13` has a ninth,
+/// and the eleventh is usually left out.
+fn stacked(degree: u8) -> impl Iterator<Item = u8> {
+    [9, 11, 13]
+        .into_iter()
+        .filter(move |&d| d <= degree && !(d == 11 && degree == 13))
+}
+
+/// Semitones from `from` up to `to`, from 0 to 11
+pub(crate) fn interval(from: Note, to: Note) -> u8 {
+    (to.to_i8().unwrap() - from.to_i8().unwrap()).rem_euclid(12) as u8
+}
+
+impl PartialEq for Chord {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical() == other.canonical()
+    }
+}
+
+impl Eq for Chord {}
+
+impl Hash for Chord {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical().hash(state)
     }
 }
 
 impl std::ops::Add<i8> for Note {
     type Output = Note;
     fn add(self, other: i8) -> Self::Output {
-        let k = (self.to_i8().unwrap() + other + 12) % 12;
+        let k = (i16::from(self.to_i8().unwrap()) + i16::from(other)).rem_euclid(12);
 
-        Note::from_i8(k).unwrap()
+        Note::from_i16(k).unwrap()
     }
 }
 
 impl std::ops::Sub<i8> for Note {
     type Output = Note;
     fn sub(self, other: i8) -> Self::Output {
-        self + (-other)
+        self + -other.rem_euclid(12)
+    }
+}
+
+/// Transposes the note, spelling it with flats if it had any, and with
+/// sharps otherwise
+impl std::ops::Add<i8> for SpelledNote {
+    type Output = SpelledNote;
+    fn add(self, other: i8) -> Self::Output {
+        let note = self.pitch_class() + other.rem_euclid(12);
+
+        match self.accidental {
+            Accidental::Flat | Accidental::DoubleFlat => SpelledNote::flat(note),
+            Accidental::Natural => SpelledNote::from(note),
+            _ => SpelledNote::sharp(note),
+        }
     }
 }
 
-impl Default for Note {
-    fn default() -> Self {
-        Note::A
+#[cfg(test)]
+mod test {
+    use crate::{Accidental, Alteration, Chord, ChordQuality, Extension, Letter, Note, SpelledNote};
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    #[test]
+    fn canonical_equality() {
+        let chord = |s| Chord::from_str(s).unwrap();
+
+        assert_eq!(chord("Cmaj7"), chord("CM7"));
+        assert_eq!(chord("Cmaj7"), chord("CΔ7"));
+        assert_eq!(chord("Cm7b5"), chord("Cø"));
+        assert_eq!(chord("Cm7b5").quality, ChordQuality::HalfDiminished);
+        assert_eq!(chord("C-7"), chord("Cmin7"));
+        assert_eq!(chord("G7b9#11"), chord("G7#11b9"));
+        assert_eq!(chord("C5").quality, ChordQuality::Power);
+        assert_ne!(chord("C7"), chord("Cmaj7"));
+        assert_ne!(chord("Cm7"), chord("Cø"));
+
+        let set: HashSet<Chord> = ["Cmaj7", "CM7", "CΔ7", "Cø", "Cm7b5"].into_iter().map(chord).collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn half_diminished_flat_fifth() {
+        // The flat fifth is part of the half-diminished chord
+        let redundant = Chord {
+            extensions: vec![Extension::Degree(Some(Alteration::Flat), 5)],
+            ..Chord::from_str("Cø").unwrap()
+        };
+
+        assert_eq!(redundant, Chord::from_str("Cø").unwrap());
+        assert_eq!(redundant.normalized().extensions, vec![]);
+        assert_eq!(Chord::from_str(&redundant.to_string()).unwrap(), redundant);
+    }
+
+    #[test]
+    fn intervals() {
+        let intervals = |s| Chord::from_str(s).unwrap().intervals();
+
+        assert_eq!(intervals("C"), vec![0, 4, 7]);
+        assert_eq!(intervals("Cm7"), vec![0, 3, 7, 10]);
+        assert_eq!(intervals("Cmaj7"), vec![0, 4, 7, 11]);
+        assert_eq!(intervals("Cdim7"), vec![0, 3, 6, 9]);
+        assert_eq!(intervals("Cø"), vec![0, 3, 6, 10]);
+        assert_eq!(intervals("C9"), vec![0, 2, 4, 7, 10]);
+        assert_eq!(intervals("C13"), vec![0, 2, 4, 7, 9, 10]);
+        assert_eq!(intervals("G7b9#11"), vec![0, 1, 4, 6, 7, 10]);
+        assert_eq!(intervals("C7#5"), vec![0, 4, 8, 10]);
+        assert_eq!(intervals("Cadd9(omit3)"), vec![0, 2, 7]);
+        assert_eq!(intervals("C5"), vec![0, 7]);
+        assert_eq!(intervals("Csus2"), vec![0, 2, 7]);
+        assert_eq!(intervals("C/Bb"), vec![0, 4, 7, 10]);
+    }
+
+    #[test]
+    fn notes() {
+        let notes = |s| Chord::from_str(s).unwrap().notes();
+
+        assert_eq!(notes("Am"), vec![Note::A, Note::C, Note::E]);
+        assert_eq!(notes("D7"), vec![Note::D, Note::FSharp, Note::A, Note::C]);
+        assert_eq!(notes("C/E"), vec![Note::E, Note::C, Note::G]);
+        assert_eq!(notes("C/F"), vec![Note::F, Note::C, Note::E, Note::G]);
+    }
+
+    #[test]
+    fn identify() {
+        let chord = |s| Chord::from_str(s).unwrap();
+        let identify = |notes: &[Note]| Chord::identify(notes);
+
+        assert_eq!(identify(&[Note::C, Note::E, Note::G])[0], chord("C"));
+        assert_eq!(identify(&[Note::E, Note::G, Note::C, Note::E])[0], chord("C/E"));
+        assert_eq!(identify(&[Note::G, Note::B, Note::D, Note::F])[0], chord("G7"));
+        assert_eq!(identify(&[Note::C, Note::E, Note::ASharp])[0], chord("C7(omit5)"));
+        assert_eq!(identify(&[Note::B, Note::D, Note::F, Note::A])[0], chord("Bø"));
+
+        let candidates = identify(&[Note::A, Note::C, Note::E, Note::G]);
+        assert_eq!(candidates[0], chord("Am7"));
+        assert!(candidates.contains(&chord("C6/A")));
+
+        assert!(identify(&[]).is_empty());
+    }
+
+    #[test]
+    fn spelled_note() {
+        let spelled = |s| SpelledNote::from_str(s).unwrap();
+
+        assert_eq!(spelled("Db"), SpelledNote::new(Letter::D, Accidental::Flat));
+        assert_eq!(spelled("Db").pitch_class(), Note::CSharp);
+        assert_eq!(spelled("B##").pitch_class(), Note::CSharp);
+        assert_eq!(spelled("Fbb").pitch_class(), Note::DSharp);
+        assert_ne!(spelled("Db"), spelled("C#"));
+        assert_ne!(Chord::from_str("Db").unwrap(), Chord::from_str("C#").unwrap());
+
+        assert_eq!(SpelledNote::flat(Note::GSharp), spelled("Ab"));
+        assert_eq!(SpelledNote::sharp(Note::GSharp), spelled("G#"));
+        assert_eq!(SpelledNote::with_letter(Note::C, Letter::B), Some(spelled("B#")));
+        assert_eq!(SpelledNote::with_letter(Note::C, Letter::G), None);
+        assert_eq!(spelled("Eb") + 2, spelled("F"));
+        assert_eq!(spelled("Eb") + 1, spelled("E"));
+        assert_eq!(spelled("Eb") + 3, spelled("Gb"));
+        assert_eq!(spelled("Eb") + (-20), spelled("G"));
+        assert_eq!(spelled("C#") + 127, spelled("G#"));
+    }
+
+    #[test]
+    fn transpose_note() {
+        assert_eq!(Note::C + 2, Note::D);
+        assert_eq!(Note::C - 1, Note::B);
+        assert_eq!(Note::C + (-20), Note::E);
+        assert_eq!(Note::C + 127, Note::G);
+        assert_eq!(Note::C + i8::MIN, Note::E);
+        assert_eq!(Note::C - 13, Note::B);
+        assert_eq!(Note::C - i8::MIN, Note::GSharp);
     }
 }
@@ -0,0 +1,83 @@
+//! Chord definitions, from the `{define}` and `{chord}` directives.
+//!
+//! ```text
+//! {define: Am base-fret 1 frets x 0 2 2 1 0 fingers 0 0 2 3 1 0}
+//! {chord: C keys 0 4 7}
+//! ```
+use crate::chords::Chord;
+use crate::selector::Condition;
+use crate::song::Song;
+use serde::Serialize;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// How to play a chord on a fretted instrument or a keyboard
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct ChordDefinition {
+    /// Name of the chord, as written
+    pub name: String,
+    /// The chord, if the name is a valid one
+    pub chord: Option<Chord>,
+    /// First fret of the diagram
+    pub base_fret: Option<u8>,
+    /// Fret of each string, relative to `base_fret`. `None` means that the
+    /// string is not played.
+    pub frets: Vec<Option<u8>>,
+    /// Finger for each string. `None` means that no finger is used.
+    pub fingers: Vec<Option<u8>>,
+    /// Keyboard keys, as semitones from the root
+    pub keys: Vec<u8>,
+    /// Defined with `{chord}` instead of `{define}`: the chord diagram
+    /// should be shown where the directive is.
+    pub show: bool,
+    /// Selector of the directive, like in `{define-guitar: ...}`
+    pub condition: Option<Condition>,
+}
+
+impl Song {
+    /// Finds the definition for `chord`. If it is defined several times,
+    /// the last definition is used.
+    pub fn chord_definition(&self, chord: &Chord) -> Option<&ChordDefinition> {
+        self.definitions
+            .iter()
+            .rev()
+            .find(|d| d.chord.as_ref() == Some(chord))
+    }
+}
+
+/// Writes the arguments of the directive, like `Am base-fret 1 frets x 0 2 2 1 0`
+impl Display for ChordDefinition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let unused = |v: &Option<u8>| match v {
+            Some(n) => n.to_string(),
+            None => "x".to_string(),
+        };
+
+        write!(f, "{}", self.name)?;
+
+        if let Some(base_fret) = self.base_fret {
+            write!(f, " base-fret {}", base_fret)?;
+        }
+        if !self.frets.is_empty() {
+            write!(f, " frets")?;
+            for fret in self.frets.iter() {
+                write!(f, " {}", unused(fret))?;
+            }
+        }
+        if !self.fingers.is_empty() {
+            write!(f, " fingers")?;
+            for finger in self.fingers.iter() {
+                write!(f, " {}", unused(finger))?;
+            }
+        }
+        if !self.keys.is_empty() {
+            write!(f, " keys")?;
+            for key in self.keys.iter() {
+                write!(f, " {}", key)?;
+            }
+        }
+
+        Ok(())
+    }
+}
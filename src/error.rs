@@ -0,0 +1,246 @@
+//! Errors returned when parsing ChordPro text.
+//!
+//! A `ParseError` points to the place in the source where parsing failed.
+//! Its `std::fmt::Display` implementation quotes the offending line and
+//! marks the span with carets:
+//! ```text
+//! malformed chord at line 3, column 12
+//!   |
+//! 3 | This is a [H7]chord
+//!   |            ^^
+//! ```
+
+use serde::Serialize;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Location in the source text
+#[derive(Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Position {
+    /// Byte offset from the start of the source
+    pub offset: usize,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column number in characters, starting at 1
+    pub column: usize,
+}
+
+impl Position {
+    /// Computes the line and column of the byte `offset` in `source`
+    pub fn new(source: &str, offset: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+        Position {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// Part of the source text, from `start` (inclusive) to `end` (exclusive)
+#[derive(Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Span between two byte offsets of `source`
+    pub fn new(source: &str, start: usize, end: usize) -> Self {
+        Span {
+            start: Position::new(source, start),
+            end: Position::new(source, end),
+        }
+    }
+}
+
+/// What went wrong while parsing
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Input that does not follow the ChordPro syntax
+    Syntax,
+    /// A `[...]` block that does not contain a valid chord
+    MalformedChord,
+    /// A note that is not valid
+    MalformedNote,
+    /// A key that is not valid
+    MalformedKey,
+    /// A `[` that is not closed in the same line
+    UnterminatedChord,
+    /// A `]` without its opening `[`
+    StrayBracket,
+    /// A `{` that is not closed in the same line
+    UnterminatedDirective,
+    /// A `{start_of_chorus}` without its `{end_of_chorus}`
+    UnterminatedChorus,
+    /// A `{start_of_X}` without its `{end_of_X}`
+    UnterminatedEnvironment,
+    /// A `{define}` or `{chord}` directive that is not valid
+    MalformedDefinition,
+    /// A `{transpose}` directive that is not a number of semitones
+    MalformedTranspose,
+    /// A directive that is not known. Unknown directives are ignored, as
+    /// the ChordPro specification says, so this is only a warning.
+    UnknownDirective,
+    /// A chord with other note names than the first chord of the song,
+    /// like `[C]` after `[Do]`. It is still read, so this is a warning.
+    MixedNoteNames,
+    /// An `{end_of_X}` without an open environment. It is ignored, so this
+    /// is only a warning.
+    StrayEnd,
+    /// A `{year}`, `{tempo}` or `{capo}` that is not a number. It is kept in
+    /// `Metadata::meta`, so this is only a warning.
+    MalformedMetadata,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ErrorKind::Syntax => "syntax error",
+                ErrorKind::MalformedChord => "malformed chord",
+                ErrorKind::MalformedNote => "malformed note",
+                ErrorKind::MalformedKey => "malformed key",
+                ErrorKind::UnterminatedChord => "unterminated chord",
+                ErrorKind::StrayBracket => "stray `]`",
+                ErrorKind::UnterminatedDirective => "unterminated directive",
+                ErrorKind::UnterminatedChorus => "unterminated chorus",
+                ErrorKind::UnterminatedEnvironment => "unterminated environment",
+                ErrorKind::MalformedDefinition => "malformed chord definition",
+                ErrorKind::MalformedTranspose => "malformed transpose",
+                ErrorKind::UnknownDirective => "unknown directive",
+                ErrorKind::MixedNoteNames => "mixed note names",
+                ErrorKind::StrayEnd => "end of environment that is not open",
+                ErrorKind::MalformedMetadata => "malformed metadata value",
+            }
+        )
+    }
+}
+
+/// Error found while parsing ChordPro text
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    pub span: Span,
+    /// Source line where the span starts, without the line break
+    pub source_line: String,
+}
+
+impl ParseError {
+    /// Error of `kind` between two byte offsets of `source`
+    pub fn new(kind: ErrorKind, source: &str, start: usize, end: usize) -> Self {
+        let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = source[start..]
+            .find('\n')
+            .map(|i| start + i)
+            .unwrap_or(source.len());
+
+        ParseError {
+            kind,
+            span: Span::new(source, start, end),
+            source_line: source[line_start..line_end].trim_end_matches('\r').to_owned(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let Span { start, end } = self.span;
+        let line_number = start.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+
+        // Spans that continue in other lines are marked until the end of the first one
+        let line_length = self.source_line.chars().count() + 1;
+        let end_column = if end.line == start.line {
+            end.column
+        } else {
+            line_length
+        };
+        let carets = end_column.saturating_sub(start.column).max(1);
+
+        writeln!(f, "{} at line {}, column {}", self.kind, start.line, start.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line_number, self.source_line)?;
+        write!(
+            f,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(start.column - 1),
+            "^".repeat(carets)
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// How serious a `Diagnostic` is
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    /// Suspicious input that is still valid ChordPro
+    Warning,
+    /// Invalid input, that makes `Song::from_str` fail
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Problem found while parsing a song with `Song::parse_lenient`
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub error: ParseError,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn position() {
+        let source = "first\nsecond line\nthird";
+
+        assert_eq!(
+            Position::new(source, 13),
+            Position {
+                offset: 13,
+                line: 2,
+                column: 8
+            }
+        );
+        assert_eq!(Position::new(source, 0).line, 1);
+        assert_eq!(Position::new(source, source.len()).column, 6);
+    }
+
+    #[test]
+    fn render() {
+        let source = "{title: Song}\n\nThis is a [H7]chord\n";
+        let error = ParseError::new(ErrorKind::MalformedChord, source, 26, 28);
+
+        assert_eq!(
+            error.to_string(),
+            "malformed chord at line 3, column 12\n  \
+             |\n\
+             3 | This is a [H7]chord\n  \
+             |            ^^"
+        );
+    }
+}
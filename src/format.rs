@@ -2,79 +2,283 @@
 //! This module has functions to convert chords and notes to strings in different formats.
 //!
 //! ## European / default format
-//! By default, `Chord` and `Note` implement `std::fmt::Display`.
+//! By default, `Chord` and `Note` implement `std::fmt::Display`. Chords keep
+//! the spelling of their notes (`Db` or `C#`), and a `Note` is written with
+//! sharps, except `Bb`.
+//!
+//! Wrap a chord in `Styled` to choose how its quality is spelled: short
+//! names (`Cm7b5`), long names (`Cmin7b5`) or jazz symbols (`Cø`).
+//! `Typographic` also takes a `ChordStyle`, and writes accidentals with
+//! their own symbols (`C♯m7♭5`) for printed songbooks. They are parsed
+//! back too.
 //!
 //! ## Latin
 //! Do, Re, Mi, etc. notation.
 //!
 //! For notes, call `latin(&Note)`.
 //! For chords, wrap them in `Latin`, which implements the `std::fmt::Display` trait.
+//! Chords written like this are parsed back by `Song::from_str`.
+//!
+//! ## German
+//! `H` is B and `B` is B flat.
+//!
+//! For notes, call `german(&Note)`. For chords, wrap them in `German`. They
+//! are parsed back with `NoteNames::German`.
+//!
+//! ## Relative to a key
+//! Wrap a chord in `Nashville` to write it in the Nashville number system
+//! (`1`, `4`, `6m`, `5/7`), or in `Roman` to write it with Roman numerals
+//! (`I`, `IV`, `vi`, `V/vii`).
+//!
+//! `Notation` chooses one of these formats, to write a whole song with
+//! `Song::to_chordpro_in`.
 //!
 
-use crate::chords::{Chord, Note};
+use crate::chords::{Accidental, Alteration, Chord, ChordQuality, Extension, Letter, Note, SpelledNote};
+use crate::key::{Key, Mode, ScaleDegree};
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
 impl std::fmt::Display for Note {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Note::A => "A",
-                Note::ASharp => "Bb",
-                Note::B => "B",
-                Note::C => "C",
-                Note::CSharp => "C#",
-                Note::D => "D",
-                Note::DSharp => "D#",
-                Note::E => "E",
-                Note::F => "F",
-                Note::FSharp => "F#",
-                Note::G => "G",
-                Note::GSharp => "G#",
+        write!(f, "{}", SpelledNote::from(*self))
+    }
+}
+
+impl Display for Letter {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Display for Accidental {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", Symbols::Ascii.accidental(*self))
+    }
+}
+
+impl Display for SpelledNote {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.letter, self.accidental)
+    }
+}
+
+impl Display for Alteration {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", Symbols::Ascii.alteration(*self))
+    }
+}
+
+/// Writes the extension as in the default `ChordStyle`
+impl Display for Extension {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", StyledExtension(self, ChordStyle::default(), Symbols::Ascii))
+    }
+}
+
+/// Characters for accidentals and the jazz minor
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Symbols {
+    /// `#`, `b` and `-`, as they are typed
+    Ascii,
+    /// `♯`, `♭` and `−`
+    Typographic,
+}
+
+impl Symbols {
+    fn accidental(self, accidental: Accidental) -> &'static str {
+        match (self, accidental) {
+            (_, Accidental::Natural) => "",
+            (Symbols::Ascii, Accidental::DoubleFlat) => "bb",
+            (Symbols::Ascii, Accidental::Flat) => "b",
+            (Symbols::Ascii, Accidental::Sharp) => "#",
+            (Symbols::Ascii, Accidental::DoubleSharp) => "##",
+            (Symbols::Typographic, Accidental::DoubleFlat) => "𝄫",
+            (Symbols::Typographic, Accidental::Flat) => "♭",
+            (Symbols::Typographic, Accidental::Sharp) => "♯",
+            (Symbols::Typographic, Accidental::DoubleSharp) => "𝄪",
+        }
+    }
+
+    fn alteration(self, alteration: Alteration) -> &'static str {
+        match alteration {
+            Alteration::Flat => self.accidental(Accidental::Flat),
+            Alteration::Sharp => self.accidental(Accidental::Sharp),
+        }
+    }
+
+    fn minus(self) -> &'static str {
+        match self {
+            Symbols::Ascii => "-",
+            Symbols::Typographic => "−",
+        }
+    }
+}
+
+/// How chord qualities are spelled
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ChordStyle {
+    /// `Cm`, `Caug`, `Cdim`, `Cm7b5`, `Cmaj7`
+    #[default]
+    Short,
+    /// `Cmin`, `Caug`, `Cdim`, `Cmin7b5`, `Cmaj7`
+    Long,
+    /// `C-`, `C+`, `C°`, `Cø`, `CΔ7`
+    Jazz,
+}
+
+struct StyledExtension<'a>(&'a Extension, ChordStyle, Symbols);
+
+impl<'a> Display for StyledExtension<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let alteration = |a: &Option<Alteration>| a.map(|a| self.2.alteration(a)).unwrap_or_default();
+
+        match (self.0, self.1) {
+            (Extension::Degree(a, n), _) => write!(f, "{}{}", alteration(a), n),
+            (Extension::Major(n), ChordStyle::Jazz) => write!(f, "Δ{}", n),
+            (Extension::Major(n), _) => write!(f, "maj{}", n),
+            (Extension::Add(a, n), _) => write!(f, "add{}{}", alteration(a), n),
+            (Extension::Omit(n), _) => write!(f, "omit{}", n),
+        }
+    }
+}
+
+/// Everything in a chord name after the root and before the bass
+struct Suffix<'a>(&'a Chord, ChordStyle, Symbols);
+
+impl<'a> Display for Suffix<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let Suffix(chord, style, symbols) = *self;
+        let half_diminished = chord.quality == ChordQuality::HalfDiminished;
+
+        let prefix = match (chord.quality, style) {
+            (ChordQuality::Minor, ChordStyle::Jazz) => symbols.minus(),
+            (ChordQuality::Minor | ChordQuality::HalfDiminished, ChordStyle::Long) => "min",
+            (ChordQuality::Minor | ChordQuality::HalfDiminished, ChordStyle::Short) => "m",
+            (ChordQuality::Augmented, ChordStyle::Jazz) => "+",
+            (ChordQuality::Augmented, _) => "aug",
+            (ChordQuality::Diminished, ChordStyle::Jazz) => "°",
+            (ChordQuality::Diminished, _) => "dim",
+            (ChordQuality::HalfDiminished, ChordStyle::Jazz) => "ø",
+            (ChordQuality::Power, _) => "5",
+            _ => "",
+        };
+        write!(f, "{}", prefix)?;
+
+        // Without symbols, a half-diminished chord is a minor seventh with
+        // a flat fifth, unless a higher degree already implies the seventh
+        let spelled_out = half_diminished && style != ChordStyle::Jazz;
+        if spelled_out && !chord.extensions.iter().any(|e| matches!(e, Extension::Degree(None, 7..))) {
+            write!(f, "7")?;
+        }
+
+        let mut extensions = chord.extensions.iter().enumerate().peekable();
+        while let Some((i, extension)) = extensions.next() {
+            match extension {
+                // Right after the root, the alteration would be read as
+                // part of the note, like the `b` in `Cb9`
+                Extension::Degree(Some(_), _) if i == 0 && prefix.is_empty() => {
+                    write!(f, "({})", StyledExtension(extension, style, symbols))?
+                }
+                Extension::Degree(None, 6) if extensions.next_if(|(_, e)| **e == Extension::Add(None, 9)).is_some() => {
+                    write!(f, "6/9")?
+                }
+                _ => write!(f, "{}", StyledExtension(extension, style, symbols))?,
             }
-        )
+        }
+
+        if spelled_out {
+            write!(f, "{}5", symbols.alteration(Alteration::Flat))?;
+        }
+
+        match chord.quality {
+            ChordQuality::Suspended2 => write!(f, "sus2"),
+            ChordQuality::Suspended4 => write!(f, "sus4"),
+            _ => Ok(()),
+        }
     }
 }
 
 impl std::fmt::Display for Chord {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.root)?;
+        write!(f, "{}", Styled(self, ChordStyle::default()))
+    }
+}
 
-        if self.minor {
-            write!(f, "m")?;
-        }
+/// Wrapper to format a chord with a `ChordStyle`
+pub struct Styled<'a>(pub &'a Chord, pub ChordStyle);
 
-        write!(f, "{}", self.others)?;
+impl<'a> Display for Styled<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let chord = self.0;
+        write!(f, "{}{}", chord.root, Suffix(chord, self.1, Symbols::Ascii))?;
 
-        if self.number != 0 {
-            write!(f, "{}", self.number)?;
+        if chord.bass != chord.root {
+            write!(f, "/{}", chord.bass)?;
         }
 
-        if self.bass != self.root {
-            write!(f, "/{}", self.bass)?;
+        Ok(())
+    }
+}
+
+impl<'a> AsRef<Chord> for Styled<'a> {
+    fn as_ref(&self) -> &Chord {
+        self.0
+    }
+}
+
+/// Wrapper to format a chord with typographic symbols, for publishing:
+/// `♯` and `♭` for accidentals, and `−` for the jazz minor (`C♯m7♭5`,
+/// `B♭Δ7`, `E−7`)
+pub struct Typographic<'a>(pub &'a Chord, pub ChordStyle);
+
+struct TypographicNote(SpelledNote);
+
+impl Display for TypographicNote {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.0.letter, Symbols::Typographic.accidental(self.0.accidental))
+    }
+}
+
+impl<'a> Display for Typographic<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let Typographic(chord, style) = *self;
+        write!(f, "{}{}", TypographicNote(chord.root), Suffix(chord, style, Symbols::Typographic))?;
+
+        if chord.bass != chord.root {
+            write!(f, "/{}", TypographicNote(chord.bass))?;
         }
 
         Ok(())
     }
 }
 
+impl<'a> AsRef<Chord> for Typographic<'a> {
+    fn as_ref(&self) -> &Chord {
+        self.0
+    }
+}
+
 /// Shows the note in latin format (Do, Re, Mi...)
 pub fn latin(n: &Note) -> impl Display {
-    match n {
-        Note::A => "La",
-        Note::ASharp => "Sib",
-        Note::B => "Si",
-        Note::C => "Do",
-        Note::CSharp => "Do#",
-        Note::D => "Re",
-        Note::DSharp => "Re#",
-        Note::E => "Mi",
-        Note::F => "Fa",
-        Note::FSharp => "Fa#",
-        Note::G => "Sol",
-        Note::GSharp => "Sol#",
+    LatinNote(SpelledNote::from(*n))
+}
+
+struct LatinNote(SpelledNote);
+
+impl Display for LatinNote {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self.0.letter {
+            Letter::C => "Do",
+            Letter::D => "Re",
+            Letter::E => "Mi",
+            Letter::F => "Fa",
+            Letter::G => "Sol",
+            Letter::A => "La",
+            Letter::B => "Si",
+        };
+
+        write!(f, "{}{}", name, self.0.accidental)
     }
 }
 
@@ -83,36 +287,207 @@ pub struct Latin<'a>(pub &'a Chord);
 
 impl<'a> Display for Latin<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "{}", latin(&self.0.root))?;
+        write!(f, "{}{}", LatinNote(self.0.root), Suffix(self.0, ChordStyle::default(), Symbols::Ascii))?;
 
-        if self.0.minor {
-            write!(f, "m")?;
+        if self.0.bass != self.0.root {
+            write!(f, "/{}", LatinNote(self.0.bass))?;
         }
 
-        if self.0.number != 0 {
-            write!(f, "{}", self.0.number)?;
+        Ok(())
+    }
+}
+
+impl<'a> AsRef<Chord> for Latin<'a> {
+    fn as_ref(&self) -> &Chord {
+        self.0
+    }
+}
+
+/// Shows the note in German format, where `H` is B and `B` is B flat
+pub fn german(n: &Note) -> impl Display {
+    GermanNote(SpelledNote::from(*n))
+}
+
+struct GermanNote(SpelledNote);
+
+impl Display for GermanNote {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match (self.0.letter, self.0.accidental) {
+            (Letter::B, Accidental::Natural) => write!(f, "H"),
+            (Letter::B, Accidental::Flat) => write!(f, "B"),
+            (Letter::B, Accidental::DoubleFlat) => write!(f, "Bb"),
+            (Letter::B, accidental) => write!(f, "H{}", accidental),
+            _ => write!(f, "{}", self.0),
         }
-        if !self.0.others.is_empty() {
-            write!(f, "{}", self.0.others)?;
+    }
+}
+
+/// Writes a key in German format, as `{key}` is read with `NoteNames::German`
+pub(crate) struct GermanKey(pub Key);
+
+impl Display for GermanKey {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0.mode {
+            Mode::Major => write!(f, "{}", GermanNote(self.0.tonic)),
+            Mode::Minor => write!(f, "{}m", GermanNote(self.0.tonic)),
         }
+    }
+}
+
+/// Wrapper to format chord in German format (`H7`, `B`, `Bm/F`)
+pub struct German<'a>(pub &'a Chord);
+
+impl<'a> Display for German<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}{}", GermanNote(self.0.root), Suffix(self.0, ChordStyle::default(), Symbols::Ascii))?;
 
         if self.0.bass != self.0.root {
-            write!(f, "/{}", latin(&self.0.bass))?;
+            write!(f, "/{}", GermanNote(self.0.bass))?;
         }
 
         Ok(())
     }
 }
 
-impl<'a> AsRef<Chord> for Latin<'a> {
+impl<'a> AsRef<Chord> for German<'a> {
+    fn as_ref(&self) -> &Chord {
+        self.0
+    }
+}
+
+/// Wrapper to format a chord in the Nashville number system, relative to a
+/// key (`1`, `6m`, `5/7`, `b7`)
+pub struct Nashville<'a>(pub &'a Chord, pub Key);
+
+impl<'a> Display for Nashville<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let Nashville(chord, key) = *self;
+        write!(f, "{}{}", key.degree(chord.root), Suffix(chord, ChordStyle::Short, Symbols::Ascii))?;
+
+        if chord.bass != chord.root {
+            write!(f, "/{}", key.degree(chord.bass))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> AsRef<Chord> for Nashville<'a> {
+    fn as_ref(&self) -> &Chord {
+        self.0
+    }
+}
+
+/// Wrapper to format a chord with Roman numerals, relative to a key (`I`,
+/// `vi`, `V/vii`, `bVII`). Minor and diminished chords are lower case.
+pub struct Roman<'a>(pub &'a Chord, pub Key);
+
+struct RomanNumeral(ScaleDegree, bool);
+
+impl Display for RomanNumeral {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        const NUMERALS: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+        let numeral = NUMERALS[(self.0.degree.clamp(1, 7) - 1) as usize];
+
+        match self.1 {
+            true => write!(f, "{}{}", self.0.accidental, numeral.to_lowercase()),
+            false => write!(f, "{}{}", self.0.accidental, numeral),
+        }
+    }
+}
+
+impl<'a> Display for Roman<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let Roman(chord, key) = *self;
+        let lower_case = matches!(
+            chord.quality,
+            ChordQuality::Minor | ChordQuality::Diminished | ChordQuality::HalfDiminished
+        );
+        // The case of the numeral tells the minor chords, and the other
+        // qualities use symbols
+        let (suffix, style) = match chord.quality {
+            ChordQuality::Minor => (
+                Chord {
+                    quality: ChordQuality::Major,
+                    ..chord.clone()
+                },
+                ChordStyle::Short,
+            ),
+            ChordQuality::Augmented | ChordQuality::Diminished | ChordQuality::HalfDiminished => {
+                (chord.clone(), ChordStyle::Jazz)
+            }
+            _ => (chord.clone(), ChordStyle::Short),
+        };
+
+        let root = key.degree(chord.root);
+        write!(f, "{}{}", RomanNumeral(root, lower_case), Suffix(&suffix, style, Symbols::Ascii))?;
+
+        // The bass is written as the chord of its degree in the key
+        if chord.bass != chord.root {
+            let bass = key.degree(chord.bass);
+            let lower_case = match key.mode {
+                Mode::Major => matches!(bass.degree, 2 | 3 | 6 | 7),
+                Mode::Minor => matches!(bass.degree, 1 | 2 | 4 | 5),
+            };
+            write!(f, "/{}", RomanNumeral(bass, lower_case))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> AsRef<Chord> for Roman<'a> {
+    fn as_ref(&self) -> &Chord {
+        self.0
+    }
+}
+
+/// Notation of the chords of a song
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Notation {
+    /// Letter names, as `Chord` is displayed
+    #[default]
+    Standard,
+    /// Do, Re, Mi, as `Latin`
+    Latin,
+    /// `H` for B and `B` for B flat, as `German`
+    German,
+    /// Nashville numbers in a key, as `Nashville`
+    Nashville(Key),
+    /// Roman numerals in a key, as `Roman`
+    Roman(Key),
+}
+
+/// Wrapper to format a chord in a `Notation`
+pub struct Notated<'a>(pub &'a Chord, pub Notation);
+
+impl<'a> Display for Notated<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.1 {
+            Notation::Standard => write!(f, "{}", self.0),
+            Notation::Latin => write!(f, "{}", Latin(self.0)),
+            Notation::German => write!(f, "{}", German(self.0)),
+            Notation::Nashville(key) => write!(f, "{}", Nashville(self.0, key)),
+            Notation::Roman(key) => write!(f, "{}", Roman(self.0, key)),
+        }
+    }
+}
+
+impl<'a> AsRef<Chord> for Notated<'a> {
     fn as_ref(&self) -> &Chord {
         self.0
     }
 }
 
 #[cfg(test)]
-mod test {
-    use crate::{latin, Chord, Latin, Note};
+pub(crate) mod test {
+    use crate::{
+        german, latin, Accidental, Alteration, Chord, ChordQuality, ChordStyle, Extension, German,
+        Key, Latin, Letter, Nashville, Note, NoteNames, Roman, SpelledNote, Styled, Typographic,
+    };
+    use num_traits::FromPrimitive;
+    use proptest::prelude::*;
+    use std::str::FromStr;
     use std::string::ToString;
 
     #[test]
@@ -133,13 +508,105 @@ mod test {
     #[test]
     fn standard_chord_with_bass() {
         let make_chord_bass = |mut c: Chord, b: Note| {
-            c.bass = b;
+            c.bass = b.into();
             c
         };
 
-        assert_eq!(make_chord_bass(Chord::major(Note::C), Note::G).to_string(), "C/G");
-        assert_eq!(make_chord_bass(Chord::major(Note::CSharp), Note::GSharp).to_string(), "C#/G#");
-        assert_eq!(make_chord_bass(Chord::minor(Note::E), Note::G).to_string(), "Em/G");
+        assert_eq!(
+            make_chord_bass(Chord::major(Note::C), Note::G).to_string(),
+            "C/G"
+        );
+        assert_eq!(
+            make_chord_bass(Chord::major(Note::CSharp), Note::GSharp).to_string(),
+            "C#/G#"
+        );
+        assert_eq!(
+            make_chord_bass(Chord::minor(Note::E), Note::G).to_string(),
+            "Em/G"
+        );
+    }
+
+    #[test]
+    fn standard_chord_with_extensions() {
+        let chord = |root, extensions| Chord {
+            extensions,
+            ..Chord::major(root)
+        };
+
+        assert_eq!(
+            chord(
+                Note::G,
+                vec![
+                    Extension::Degree(None, 7),
+                    Extension::Degree(Some(Alteration::Flat), 9),
+                    Extension::Degree(Some(Alteration::Sharp), 11)
+                ]
+            )
+            .to_string(),
+            "G7b9#11"
+        );
+        assert_eq!(
+            chord(Note::C, vec![Extension::Add(None, 9)]).to_string(),
+            "Cadd9"
+        );
+        assert_eq!(
+            chord(Note::C, vec![Extension::Degree(Some(Alteration::Flat), 9)]).to_string(),
+            "C(b9)"
+        );
+        assert_eq!(
+            Latin(&Chord {
+                quality: ChordQuality::Suspended4,
+                ..chord(Note::D, vec![Extension::Degree(None, 7)])
+            })
+            .to_string(),
+            "Re7sus4"
+        );
+    }
+
+    #[test]
+    fn styled_chord() {
+        let styles = |s: &str| {
+            let chord = Chord::from_str(s).unwrap();
+            [ChordStyle::Short, ChordStyle::Long, ChordStyle::Jazz]
+                .map(|style| Styled(&chord, style).to_string())
+        };
+
+        assert_eq!(styles("Cm7"), ["Cm7", "Cmin7", "C-7"]);
+        assert_eq!(styles("Cm7b5"), ["Cm7b5", "Cmin7b5", "Cø"]);
+        assert_eq!(styles("Cm9b5"), ["Cm9b5", "Cmin9b5", "Cø9"]);
+        assert_eq!(styles("CΔ7"), ["Cmaj7", "Cmaj7", "CΔ7"]);
+        assert_eq!(styles("Caug"), ["Caug", "Caug", "C+"]);
+        assert_eq!(styles("Cdim7"), ["Cdim7", "Cdim7", "C°7"]);
+        assert_eq!(styles("C7sus"), ["C7sus4", "C7sus4", "C7sus4"]);
+        assert_eq!(styles("C5"), ["C5", "C5", "C5"]);
+    }
+
+    #[test]
+    fn typographic_chord() {
+        let typographic =
+            |s: &str, style| Typographic(&Chord::from_str(s).unwrap(), style).to_string();
+
+        assert_eq!(typographic("C#m7b5", ChordStyle::Short), "C♯m7♭5");
+        assert_eq!(typographic("C#m7b5", ChordStyle::Jazz), "C♯ø");
+        assert_eq!(typographic("Bbmaj7", ChordStyle::Jazz), "B♭Δ7");
+        assert_eq!(typographic("Em7", ChordStyle::Jazz), "E−7");
+        assert_eq!(typographic("G7b9#11/F", ChordStyle::Short), "G7♭9♯11/F");
+        assert_eq!(typographic("C(b9)", ChordStyle::Short), "C(♭9)");
+        assert_eq!(typographic("Abb/F##", ChordStyle::Short), "A𝄫/F𝄪");
+    }
+
+    #[test]
+    fn spelled_chord() {
+        let respell = |s: &str| Chord::from_str(s).unwrap().to_string();
+
+        assert_eq!(respell("Db"), "Db");
+        assert_eq!(respell("C#m7/G#"), "C#m7/G#");
+        assert_eq!(respell("Abb/Gb"), "Abb/Gb");
+        assert_eq!(respell("F##"), "F##");
+        assert_eq!(
+            Latin(&Chord::from_str("Dbm/Ab").unwrap()).to_string(),
+            "Rebm/Lab"
+        );
     }
 
     #[test]
@@ -160,12 +627,175 @@ mod test {
     #[test]
     fn latin_chord_bass() {
         let make_chord_bass = |mut c: Chord, b: Note| {
-            c.bass = b;
+            c.bass = b.into();
             c
         };
-        
-        assert_eq!(Latin(&make_chord_bass(Chord::major(Note::C), Note::G)).to_string(), "Do/Sol");
-        assert_eq!(Latin(&make_chord_bass(Chord::major(Note::CSharp), Note::GSharp)).to_string(), "Do#/Sol#");
-        assert_eq!(Latin(&make_chord_bass(Chord::minor(Note::E), Note::G)).to_string(), "Mim/Sol");
+
+        assert_eq!(
+            Latin(&make_chord_bass(Chord::major(Note::C), Note::G)).to_string(),
+            "Do/Sol"
+        );
+        assert_eq!(
+            Latin(&make_chord_bass(Chord::major(Note::CSharp), Note::GSharp)).to_string(),
+            "Do#/Sol#"
+        );
+        assert_eq!(
+            Latin(&make_chord_bass(Chord::minor(Note::E), Note::G)).to_string(),
+            "Mim/Sol"
+        );
+    }
+
+    #[test]
+    fn nashville_chord() {
+        let nashville = |s: &str, key: &str| {
+            let key = Key::from_str(key).unwrap();
+            Nashville(&Chord::from_str(s).unwrap(), key).to_string()
+        };
+
+        assert_eq!(nashville("C", "C"), "1");
+        assert_eq!(nashville("F", "C"), "4");
+        assert_eq!(nashville("Am", "C"), "6m");
+        assert_eq!(nashville("G/B", "C"), "5/7");
+        assert_eq!(nashville("Bb", "C"), "b7");
+        assert_eq!(nashville("E7", "Am"), "57");
+        assert_eq!(nashville("Ebmaj7", "Eb"), "1maj7");
+        assert_eq!(nashville("D7b9", "G"), "57b9");
+    }
+
+    #[test]
+    fn roman_chord() {
+        let roman = |s: &str, key: &str| {
+            let key = Key::from_str(key).unwrap();
+            Roman(&Chord::from_str(s).unwrap(), key).to_string()
+        };
+
+        assert_eq!(roman("C", "C"), "I");
+        assert_eq!(roman("F", "C"), "IV");
+        assert_eq!(roman("Am", "C"), "vi");
+        assert_eq!(roman("G/B", "C"), "V/vii");
+        assert_eq!(roman("Am7", "C"), "vi7");
+        assert_eq!(roman("Bdim", "C"), "vii°");
+        assert_eq!(roman("Bm7b5", "C"), "viiø");
+        assert_eq!(roman("Bb", "C"), "bVII");
+        assert_eq!(roman("E7", "Am"), "V7");
+        assert_eq!(roman("Am/C", "Am"), "i/III");
+    }
+
+    #[test]
+    fn german_note() {
+        assert_eq!(german(&Note::B).to_string(), "H");
+        assert_eq!(german(&Note::ASharp).to_string(), "B");
+        assert_eq!(german(&Note::C).to_string(), "C");
+    }
+
+    #[test]
+    fn german_chord() {
+        let german = |s| German(&Chord::from_str(s).unwrap()).to_string();
+
+        assert_eq!(german("B7"), "H7");
+        assert_eq!(german("Bb"), "B");
+        assert_eq!(german("Bbm/F"), "Bm/F");
+        assert_eq!(german("G/B"), "G/H");
+        assert_eq!(german("B#"), "H#");
+        assert_eq!(german("Bbb"), "Bb");
+        assert_eq!(german("F#m"), "F#m");
+    }
+
+    fn spelled_note() -> impl Strategy<Value = SpelledNote> {
+        let accidental = proptest::sample::select(
+            &[
+                Accidental::DoubleFlat,
+                Accidental::Flat,
+                Accidental::Natural,
+                Accidental::Sharp,
+                Accidental::DoubleSharp,
+            ][..],
+        );
+
+        (
+            (0..7i8).prop_map(|l| Letter::from_i8(l).unwrap()),
+            accidental,
+        )
+            .prop_map(|(letter, accidental)| SpelledNote::new(letter, accidental))
+    }
+
+    fn extension() -> impl Strategy<Value = Extension> {
+        let alteration =
+            proptest::option::of(prop_oneof![Just(Alteration::Flat), Just(Alteration::Sharp)]);
+        let number = || proptest::sample::select(&[2u8, 3, 4, 5, 6, 7, 9, 11, 13][..]);
+
+        prop_oneof![
+            (alteration.clone(), number()).prop_map(|(a, n)| Extension::Degree(a, n)),
+            proptest::sample::select(&[7u8, 9, 11, 13][..]).prop_map(Extension::Major),
+            (alteration, number()).prop_map(|(a, n)| Extension::Add(a, n)),
+            number().prop_map(Extension::Omit),
+        ]
+    }
+
+    pub(crate) fn chord() -> impl Strategy<Value = Chord> {
+        let quality = proptest::sample::select(
+            &[
+                ChordQuality::Major,
+                ChordQuality::Minor,
+                ChordQuality::Augmented,
+                ChordQuality::Diminished,
+                ChordQuality::HalfDiminished,
+                ChordQuality::Suspended2,
+                ChordQuality::Suspended4,
+                ChordQuality::Power,
+            ][..],
+        );
+
+        (
+            spelled_note(),
+            quality,
+            proptest::collection::vec(extension(), 0..4),
+            spelled_note(),
+        )
+            .prop_map(|(root, quality, extensions, bass)| Chord {
+                root,
+                quality,
+                // A power chord is written as a fifth, so it has no other extensions
+                extensions: if quality == ChordQuality::Power {
+                    vec![]
+                } else {
+                    extensions
+                },
+                bass,
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn chord_roundtrip(c in chord()) {
+            prop_assert_eq!(Chord::from_str(&c.to_string()).unwrap(), c.clone());
+
+            for style in [ChordStyle::Long, ChordStyle::Jazz] {
+                prop_assert_eq!(Chord::from_str(&Styled(&c, style).to_string()).unwrap(), c.clone());
+            }
+            for style in [ChordStyle::Short, ChordStyle::Long, ChordStyle::Jazz] {
+                prop_assert_eq!(Chord::from_str(&Typographic(&c, style).to_string()).unwrap(), c.clone());
+            }
+            prop_assert_eq!(Chord::parse_with(&Latin(&c).to_string(), NoteNames::Latin).unwrap(), c.clone());
+            prop_assert_eq!(Chord::parse_with(&German(&c).to_string(), NoteNames::German).unwrap(), c.clone());
+        }
+
+        #[test]
+        fn relative_chord_roundtrip(c in chord(), tonic in 0..12i8, minor in any::<bool>()) {
+            let key = match minor {
+                true => Key::minor(Note::A).transpose(tonic),
+                false => Key::major(Note::C).transpose(tonic),
+            };
+            // Notes far from the scale are written as in the key
+            let c = Chord {
+                root: key.note(key.degree(c.root)),
+                bass: key.note(key.degree(c.bass)),
+                ..c
+            };
+
+            for written in [Nashville(&c, key).to_string(), Roman(&c, key).to_string()] {
+                prop_assert_eq!(Chord::from_relative(&written, key).unwrap(), c.clone(), "{}", written);
+            }
+        }
     }
 }
@@ -0,0 +1,241 @@
+//! Contains the `Grid` section and its components.
+//!
+//! A grid (`{start_of_grid}`) is a chord chart made of rows of measures:
+//! ```text
+//! {start_of_grid: shape="1+4x4+1"}
+//! Intro |: C . . . | G . . . | Am . . . | F . G . :| x2
+//! ```
+//! Text before the first bar line is the row margin, and text after the
+//! last one is a comment.
+use crate::chords::Chord;
+use crate::format::{Notated, Notation};
+use serde::Serialize;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Bar line between measures
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum BarLine {
+    /// `|`
+    Single,
+    /// `||`
+    Double,
+    /// `|.`
+    End,
+    /// `|:`
+    RepeatStart,
+    /// `||:`: a double bar that starts a repeat
+    DoubleRepeatStart,
+    /// `:|`
+    RepeatEnd,
+    /// `:||`: a double bar that ends a repeat
+    DoubleRepeatEnd,
+    /// `:|:`
+    RepeatBoth,
+    /// `|1`, `|2`...: starts a volta bracket with that number
+    Volta(u8),
+    /// `:|2`, `:|3`...: ends a repeat and starts a volta bracket with that
+    /// number
+    RepeatEndVolta(u8),
+}
+
+/// Contents of a beat in a measure
+#[derive(Serialize, Debug, PartialEq, Clone)]
+#[serde(tag = "class", content = "content")]
+#[non_exhaustive]
+pub enum GridCell {
+    /// A chord
+    Chord(Chord),
+    /// Several chords in the same beat, like `C~G`
+    Split(Vec<Chord>),
+    /// `.`: the previous chord continues
+    Continue,
+    /// `%`: repeats the previous measure
+    RepeatMeasure,
+    /// `%%`: repeats the previous two measures
+    RepeatTwoMeasures,
+    /// A cell that is not valid, kept as text by lenient parsing
+    Text(String),
+}
+
+/// Beats of a measure and the bar line that closes it
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct Measure {
+    pub cells: Vec<GridCell>,
+    pub end: BarLine,
+}
+
+/// A line of the grid
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+pub struct GridRow {
+    /// Text before the first bar line
+    pub margin: Option<String>,
+    /// Bar line that opens the row, or `None` if the row has no measures
+    pub start: Option<BarLine>,
+    pub measures: Vec<Measure>,
+    /// Text after the last bar line
+    pub comment: Option<String>,
+}
+
+/// Layout of the grid, as in `shape="1+4x2+4"`
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GridShape {
+    /// Cells of the left margin
+    pub margin: u8,
+    pub measures: u8,
+    pub beats: u8,
+    /// Cells of the right margin
+    pub comment: u8,
+}
+
+/// Chord grid between `{start_of_grid}` and `{end_of_grid}`
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+pub struct Grid {
+    pub label: Option<String>,
+    pub shape: Option<GridShape>,
+    pub rows: Vec<GridRow>,
+}
+
+impl Grid {
+    /// Iterates all the chords in the grid
+    pub fn chords(&self) -> impl Iterator<Item = &Chord> {
+        self.rows
+            .iter()
+            .flat_map(|row| row.measures.iter())
+            .flat_map(|measure| measure.cells.iter())
+            .flat_map(|cell| match cell {
+                GridCell::Chord(c) => std::slice::from_ref(c),
+                GridCell::Split(chords) => chords.as_slice(),
+                _ => &[],
+            })
+    }
+
+    /// Iterates mutably all the chords in the grid
+    pub fn chords_mut(&mut self) -> impl Iterator<Item = &mut Chord> {
+        self.rows
+            .iter_mut()
+            .flat_map(|row| row.measures.iter_mut())
+            .flat_map(|measure| measure.cells.iter_mut())
+            .flat_map(|cell| match cell {
+                GridCell::Chord(c) => std::slice::from_mut(c),
+                GridCell::Split(chords) => chords.as_mut_slice(),
+                _ => &mut [],
+            })
+    }
+}
+
+impl Display for BarLine {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            BarLine::Single => write!(f, "|"),
+            BarLine::Double => write!(f, "||"),
+            BarLine::End => write!(f, "|."),
+            BarLine::RepeatStart => write!(f, "|:"),
+            BarLine::DoubleRepeatStart => write!(f, "||:"),
+            BarLine::RepeatEnd => write!(f, ":|"),
+            BarLine::DoubleRepeatEnd => write!(f, ":||"),
+            BarLine::RepeatBoth => write!(f, ":|:"),
+            BarLine::Volta(n) => write!(f, "|{}", n),
+            BarLine::RepeatEndVolta(n) => write!(f, ":|{}", n),
+        }
+    }
+}
+
+impl Display for GridCell {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", NotatedCell(self, Notation::Standard))
+    }
+}
+
+/// Writes a cell with its chords in a `Notation`
+struct NotatedCell<'a>(&'a GridCell, Notation);
+
+impl<'a> Display for NotatedCell<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let NotatedCell(cell, notation) = *self;
+
+        match cell {
+            GridCell::Chord(c) => write!(f, "{}", Notated(c, notation)),
+            GridCell::Split(chords) => {
+                for (i, c) in chords.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "~")?;
+                    }
+                    write!(f, "{}", Notated(c, notation))?;
+                }
+                Ok(())
+            }
+            GridCell::Continue => write!(f, "."),
+            GridCell::RepeatMeasure => write!(f, "%"),
+            GridCell::RepeatTwoMeasures => write!(f, "%%"),
+            GridCell::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl Display for GridRow {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", NotatedRow(self, Notation::Standard))
+    }
+}
+
+/// Writes a row with its chords in a `Notation`
+pub(crate) struct NotatedRow<'a>(pub &'a GridRow, pub Notation);
+
+impl<'a> Display for NotatedRow<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let NotatedRow(row, notation) = *self;
+        let mut tokens: Vec<String> = Vec::new();
+
+        tokens.extend(row.margin.clone());
+        tokens.extend(row.start.map(|bar| bar.to_string()));
+        for measure in row.measures.iter() {
+            tokens.extend(measure.cells.iter().map(|cell| NotatedCell(cell, notation).to_string()));
+            tokens.push(measure.end.to_string());
+        }
+        tokens.extend(row.comment.clone());
+
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+impl Display for GridShape {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}+{}x{}+{}",
+            self.margin, self.measures, self.beats, self.comment
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chords::Note;
+
+    #[test]
+    fn display_row() {
+        let row = GridRow {
+            margin: Some("Intro".to_string()),
+            start: Some(BarLine::RepeatStart),
+            measures: vec![
+                Measure {
+                    cells: vec![GridCell::Chord(Chord::major(Note::C)), GridCell::Continue],
+                    end: BarLine::Single,
+                },
+                Measure {
+                    cells: vec![
+                        GridCell::Split(vec![Chord::major(Note::F), Chord::major(Note::G)]),
+                        GridCell::RepeatMeasure,
+                    ],
+                    end: BarLine::RepeatEnd,
+                },
+            ],
+            comment: Some("x2".to_string()),
+        };
+
+        assert_eq!(row.to_string(), "Intro |: C . | F~G % :| x2");
+    }
+}
@@ -1,42 +1,143 @@
+use crate::chords::Chord;
 use crate::song::{Chunk, Line, Section, Song};
-use std::iter::{once, Once};
+use std::iter::{empty, once, Empty, Once};
 use std::slice::Iter;
 use std::slice::IterMut;
 
 impl Song {
-    pub fn iter(&self) -> Iter<Section> {
+    pub fn iter(&self) -> Iter<'_, Section> {
         self.song.iter()
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<Section> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, Section> {
         self.song.iter_mut()
     }
+
+    /// Iterates the sections, showing `{chorus}` recalls as `recall` says
+    pub fn sections(&self, recall: ChorusRecall) -> Sections<'_> {
+        Sections {
+            sections: self.song.iter(),
+            recall,
+            last_chorus: None,
+        }
+    }
+
+    /// Iterates all the chords of the song in order, including the ones in
+    /// grids
+    pub fn chords(&self) -> impl Iterator<Item = &Chord> {
+        self.song.iter().flat_map(|section| section.chords())
+    }
+
+    /// Finds the chorus recalled by the `{chorus}` at `index`: the last
+    /// unconditional one before it in the song
+    pub fn resolve_chorus(&self, index: usize) -> Option<&Section> {
+        self.song
+            .get(..index)?
+            .iter()
+            .rev()
+            .find(|s| matches!(s, Section::Chorus(_)))
+    }
+}
+
+/// How a `{chorus}` recall (`Section::ChorusRef`) is shown
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ChorusRecall {
+    /// As the `Section::ChorusRef` itself, that renderers show as a
+    /// "Chorus" heading
+    Compact,
+    /// As the recalled `Section::Chorus`, repeating it in full
+    Expanded,
+}
+
+/// Iterates the sections of a song, resolving `{chorus}` recalls.
+///
+/// Recalls without a previous chorus are always kept as they are. A
+/// conditional chorus, like `{start_of_chorus-guitar}`, is never recalled;
+/// call `Song::select` first to recall the choruses of an instrument or user.
+pub struct Sections<'a> {
+    sections: Iter<'a, Section>,
+    recall: ChorusRecall,
+    last_chorus: Option<&'a Section>,
 }
 
+impl<'a> Iterator for Sections<'a> {
+    type Item = &'a Section;
+    fn next(&mut self) -> Option<Self::Item> {
+        let section = self.sections.next()?;
+
+        match (section, self.recall, self.last_chorus) {
+            (Section::Chorus(_), _, _) => self.last_chorus = Some(section),
+            (Section::ChorusRef(_), ChorusRecall::Expanded, Some(chorus)) => return Some(chorus),
+            _ => {}
+        }
+
+        Some(section)
+    }
+}
+
+/// Iterates the lines of a section.
+///
+/// Sections without lyrics, like tablature and grids, have no lines.
 pub enum SectionIterator<'a> {
     Paragraph(Iter<'a, Line>),
     Line(Once<&'a Line>),
+    Empty(Empty<&'a Line>),
 }
 
+/// Iterates mutably the lines of a section.
+///
+/// Sections without lyrics, like tablature and grids, have no lines.
 pub enum SectionMutIterator<'a> {
     Paragraph(IterMut<'a, Line>),
     Line(Once<&'a mut Line>),
+    Empty(Empty<&'a mut Line>),
 }
 
 impl Section {
-    pub fn iter(&self) -> SectionIterator {
+    pub fn iter(&self) -> SectionIterator<'_> {
         match self {
-            Section::Chorus(p) => SectionIterator::Paragraph(p.0.iter()),
+            Section::Chorus(e) => SectionIterator::Paragraph(e.content.0.iter()),
             Section::Verse(p) => SectionIterator::Paragraph(p.0.iter()),
             Section::Comment(l) => SectionIterator::Line(once(l)),
+            Section::Environment(_, e) => SectionIterator::Paragraph(e.content.0.iter()),
+            Section::Conditional(_, s) => s.iter(),
+            Section::Tab(_) | Section::Grid(_) | Section::ChorusRef(_) | Section::Transpose(_) => {
+                SectionIterator::Empty(empty())
+            }
+        }
+    }
+
+    /// Iterates the chords of the section, including the ones in a grid
+    pub fn chords(&self) -> impl Iterator<Item = &Chord> {
+        let mut inner = self;
+        while let Section::Conditional(_, s) = inner {
+            inner = s;
         }
+        let grid = match inner {
+            Section::Grid(grid) => Some(grid),
+            _ => None,
+        };
+
+        grid.into_iter().flat_map(|grid| grid.chords()).chain(
+            self.iter()
+                .flat_map(|line| line.iter())
+                .filter_map(|chunk| match chunk {
+                    Chunk::Chord(c) => Some(c),
+                    _ => None,
+                }),
+        )
     }
 
-    pub fn iter_mut(&mut self) -> SectionMutIterator {
+    pub fn iter_mut(&mut self) -> SectionMutIterator<'_> {
         match self {
-            Section::Chorus(p) => SectionMutIterator::Paragraph(p.0.iter_mut()),
+            Section::Chorus(e) => SectionMutIterator::Paragraph(e.content.0.iter_mut()),
             Section::Verse(p) => SectionMutIterator::Paragraph(p.0.iter_mut()),
             Section::Comment(l) => SectionMutIterator::Line(once(l)),
+            Section::Environment(_, e) => SectionMutIterator::Paragraph(e.content.0.iter_mut()),
+            Section::Conditional(_, s) => s.iter_mut(),
+            Section::Tab(_) | Section::Grid(_) | Section::ChorusRef(_) | Section::Transpose(_) => {
+                SectionMutIterator::Empty(empty())
+            }
         }
     }
 }
@@ -47,6 +148,7 @@ impl<'a> Iterator for SectionIterator<'a> {
         match self {
             SectionIterator::Paragraph(p) => p.next(),
             SectionIterator::Line(l) => l.next(),
+            SectionIterator::Empty(e) => e.next(),
         }
     }
 }
@@ -57,16 +159,100 @@ impl<'a> Iterator for SectionMutIterator<'a> {
         match self {
             SectionMutIterator::Paragraph(p) => p.next(),
             SectionMutIterator::Line(l) => l.next(),
+            SectionMutIterator::Empty(e) => e.next(),
         }
     }
 }
 
 impl Line {
-    pub fn iter(&self) -> Iter<Chunk> {
+    pub fn iter(&self) -> Iter<'_, Chunk> {
         self.0.iter()
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<Chunk> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, Chunk> {
         self.0.iter_mut()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::ChorusRecall;
+    use crate::song::{Section, Song};
+    use std::str::FromStr;
+
+    #[test]
+    fn chorus_recall() {
+        let song = Song::from_str(
+            "{chorus}\n\n{soc}\nFirst\n{eoc}\n\nVerse\n\n{chorus: Again}\n\n{soc}\nSecond\n{eoc}\n{chorus}",
+        )
+        .expect("Failed to parse song");
+
+        let compact: Vec<&Section> = song.sections(ChorusRecall::Compact).collect();
+        let expanded: Vec<&Section> = song.sections(ChorusRecall::Expanded).collect();
+
+        assert_eq!(compact, song.iter().collect::<Vec<_>>());
+        assert_eq!(expanded[0], &Section::ChorusRef(None));
+        assert_eq!(expanded[3], &song.song[1]);
+        assert_eq!(expanded[5], &song.song[4]);
+
+        assert_eq!(song.resolve_chorus(0), None);
+        assert_eq!(song.resolve_chorus(3), Some(&song.song[1]));
+        assert_eq!(song.resolve_chorus(5), Some(&song.song[4]));
+        assert_eq!(song.resolve_chorus(song.song.len() + 1), None);
+    }
+
+    #[test]
+    fn conditional_chorus_recall() {
+        use crate::selector::Selector;
+
+        let song = Song::from_str("{soc}\nAll\n{eoc}\n\n{soc-guitar}\nGuitar\n{eoc}\n\n{chorus}")
+            .expect("Failed to parse song");
+        let expanded: Vec<&Section> = song.sections(ChorusRecall::Expanded).collect();
+        assert_eq!(expanded[2], &song.song[0]);
+        assert_eq!(song.resolve_chorus(2), Some(&song.song[0]));
+
+        let selector = |instrument: &str| Selector {
+            instrument: Some(instrument.to_string()),
+            user: None,
+        };
+
+        let guitar = song.select(&selector("guitar"));
+        let expanded: Vec<&Section> = guitar.sections(ChorusRecall::Expanded).collect();
+        assert_eq!(expanded[2], &guitar.song[1]);
+        assert!(matches!(expanded[2], Section::Chorus(_)));
+
+        let piano = song.select(&selector("piano"));
+        let expanded: Vec<&Section> = piano.sections(ChorusRecall::Expanded).collect();
+        assert_eq!(expanded, vec![&piano.song[0], &piano.song[0]]);
+
+        let only = Song::from_str("{soc-guitar}\nGuitar\n{eoc}\n\n{chorus}")
+            .expect("Failed to parse song");
+        let expanded: Vec<&Section> = only.sections(ChorusRecall::Expanded).collect();
+        assert_eq!(expanded[1], &Section::ChorusRef(None));
+        assert_eq!(only.resolve_chorus(1), None);
+    }
+
+    #[test]
+    fn labelled_chorus_recall() {
+        let song = Song::from_str(
+            "{start_of_chorus: Chorus 2}\nSecond\n{end_of_chorus}\n\n{chorus: Chorus 2}",
+        )
+        .expect("Failed to parse song");
+        let expanded: Vec<&Section> = song.sections(ChorusRecall::Expanded).collect();
+
+        assert!(
+            matches!(&song.song[0], Section::Chorus(e) if e.label.as_deref() == Some("Chorus 2"))
+        );
+        assert_eq!(expanded[1], &song.song[0]);
+        assert_eq!(song.resolve_chorus(1), Some(&song.song[0]));
+    }
+
+    #[test]
+    fn chords() {
+        let song = Song::from_str("[C]One [G]two\n\n{sog}\n| Am . F . |\n{eog}\n{c: [D]}")
+            .expect("Failed to parse song");
+        let chords: Vec<String> = song.chords().map(|c| c.to_string()).collect();
+
+        assert_eq!(chords, vec!["C", "G", "Am", "F", "D"]);
+    }
+}
@@ -0,0 +1,241 @@
+//! Contains the `Key` of a song, used to spell its notes.
+//!
+//! In a key, every note is written with the letter of its scale degree, so
+//! that `Bb` is used in F major and `D#` in E minor:
+//! ```
+//! # use chordpro::{Key, Note};
+//! # use std::str::FromStr;
+//! let key = Key::from_str("Em").unwrap();
+//! assert_eq!(key.spell(Note::DSharp).to_string(), "D#");
+//! ```
+use crate::chords::{interval, Accidental, Letter, Note, NoteNames, SpelledNote};
+use crate::format::GermanKey;
+use crate::song::Song;
+use num_traits::ToPrimitive;
+use serde::Serialize;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Mode of a key
+#[derive(Copy, Serialize, Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub enum Mode {
+    #[default]
+    Major,
+    Minor,
+}
+
+/// A key, like `F` or `Em`
+#[derive(Copy, Serialize, Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub struct Key {
+    pub tonic: SpelledNote,
+    pub mode: Mode,
+}
+
+/// Degree of the scale of a key, like the `b7` of `bVII`
+#[derive(Copy, Serialize, Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub struct ScaleDegree {
+    /// From 1 (the tonic) to 7
+    pub degree: u8,
+    /// Raises or lowers the note of the scale
+    pub accidental: Accidental,
+}
+
+impl ScaleDegree {
+    pub fn new(degree: u8, accidental: Accidental) -> Self {
+        ScaleDegree { degree, accidental }
+    }
+}
+
+impl Mode {
+    /// Semitones from the tonic to each degree of the scale
+    pub(crate) fn scale(&self) -> [u8; 7] {
+        match self {
+            Mode::Major => [0, 2, 4, 5, 7, 9, 11],
+            Mode::Minor => [0, 2, 3, 5, 7, 8, 10],
+        }
+    }
+}
+
+/// Letters from the tonic to each note of the octave, in semitones from the
+/// tonic. Notes out of the scale are spelled as a lowered degree in major
+/// keys (`Bb` in C) and as a raised one in minor keys (`D#` in E minor),
+/// except the tritone (always a raised fourth) and the minor second (always
+/// a lowered second).
+const LETTER_STEPS: [i8; 12] = [0, 1, 1, 2, 2, 3, 3, 4, 5, 5, 6, 6];
+
+impl Key {
+    pub fn new(tonic: SpelledNote, mode: Mode) -> Self {
+        Key { tonic, mode }
+    }
+
+    pub fn major(tonic: impl Into<SpelledNote>) -> Self {
+        Key::new(tonic.into(), Mode::Major)
+    }
+
+    pub fn minor(tonic: impl Into<SpelledNote>) -> Self {
+        Key::new(tonic.into(), Mode::Minor)
+    }
+
+    /// Key signature, in fifths: the number of sharps, or minus the
+    /// number of flats
+    pub fn signature(&self) -> i8 {
+        let letter = match self.tonic.letter {
+            Letter::F => -1,
+            Letter::C => 0,
+            Letter::G => 1,
+            Letter::D => 2,
+            Letter::A => 3,
+            Letter::E => 4,
+            Letter::B => 5,
+        };
+        let mode = match self.mode {
+            Mode::Major => 0,
+            Mode::Minor => -3,
+        };
+
+        letter + 7 * self.tonic.accidental.semitones() + mode
+    }
+
+    /// Spells `note` as it is written in this key
+    pub fn spell(&self, note: Note) -> SpelledNote {
+        let steps = LETTER_STEPS[interval(self.tonic.pitch_class(), note) as usize];
+
+        SpelledNote::with_letter(note, self.tonic.letter.step(steps)).unwrap_or_else(|| note.into())
+    }
+
+    /// Note of a degree of the scale of the key
+    pub fn note(&self, degree: ScaleDegree) -> SpelledNote {
+        let index = (degree.degree.clamp(1, 7) - 1) as usize;
+        let semitones = self.mode.scale()[index] as i8 + degree.accidental.semitones();
+        let note = self.tonic.pitch_class() + semitones;
+
+        SpelledNote::with_letter(note, self.tonic.letter.step(index as i8)).unwrap_or_else(|| self.spell(note))
+    }
+
+    /// Degree of the scale of `note`, from the letter it is written with:
+    /// `Bb` is `b7` in C and `A#` is `#6`. Notes more than a semitone away
+    /// from the degree of their letter are spelled as in the key first.
+    pub fn degree(&self, note: SpelledNote) -> ScaleDegree {
+        let letter = |note: SpelledNote| note.letter.to_i8().unwrap();
+        let degree_of = |note: SpelledNote| {
+            let index = (letter(note) - letter(self.tonic)).rem_euclid(7);
+            let semitones = interval(self.tonic.pitch_class(), note.pitch_class()) as i8;
+            let difference = (semitones - self.mode.scale()[index as usize] as i8 + 6).rem_euclid(12) - 6;
+
+            match difference {
+                -1..=1 => Accidental::from_semitones(difference).map(|a| ScaleDegree::new(index as u8 + 1, a)),
+                _ => None,
+            }
+        };
+
+        degree_of(note)
+            .or_else(|| degree_of(self.spell(note.pitch_class())))
+            .unwrap_or_default()
+    }
+
+    /// The key `semitones` higher, spelled with the fewest accidentals
+    pub fn transpose(&self, semitones: i8) -> Key {
+        let tonic = self.tonic.pitch_class() + semitones.rem_euclid(12);
+
+        [SpelledNote::sharp(tonic), SpelledNote::flat(tonic)]
+            .into_iter()
+            .map(|tonic| Key::new(tonic, self.mode))
+            .min_by_key(|key| key.signature().abs())
+            .unwrap()
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.mode {
+            Mode::Major => write!(f, "{}", self.tonic),
+            Mode::Minor => write!(f, "{}m", self.tonic),
+        }
+    }
+}
+
+impl Song {
+    /// The `{key}` of the song, read with the note names the song was
+    /// parsed with
+    pub fn key(&self) -> Option<Key> {
+        let key = self.metadata.key.as_deref()?;
+        Key::parse_with(key, self.metadata.names).ok()
+    }
+
+    /// Sets the `{key}` of the song, written with its note names
+    pub fn set_key(&mut self, key: Key) {
+        let key = match self.metadata.names {
+            NoteNames::German => GermanKey(key).to_string(),
+            _ => key.to_string(),
+        };
+        self.metadata.key = Some(key);
+    }
+}
+
+/// Writes the degree as a number, like `b7`
+impl Display for ScaleDegree {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.accidental, self.degree)
+    }
+}
+
+impl From<Note> for Key {
+    /// Major key of `note`, spelled with the fewest accidentals
+    fn from(note: Note) -> Self {
+        Key::major(Note::C).transpose(interval(Note::C, note) as i8)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn spell() {
+        let key = |s| Key::from_str(s).unwrap();
+
+        assert_eq!(key("F").spell(Note::ASharp).to_string(), "Bb");
+        assert_eq!(key("Em").spell(Note::DSharp).to_string(), "D#");
+        assert_eq!(key("Dm").spell(Note::CSharp).to_string(), "C#");
+        assert_eq!(key("C").spell(Note::GSharp).to_string(), "Ab");
+        assert_eq!(key("C").spell(Note::FSharp).to_string(), "F#");
+        assert_eq!(key("Db").spell(Note::FSharp).to_string(), "Gb");
+        assert_eq!(key("F#").spell(Note::F).to_string(), "E#");
+    }
+
+    #[test]
+    fn degree() {
+        let key = |s| Key::from_str(s).unwrap();
+        let note = |s| SpelledNote::from_str(s).unwrap();
+        assert_eq!(key("C").degree(note("G")).to_string(), "5");
+        assert_eq!(key("C").degree(note("Bb")).to_string(), "b7");
+        assert_eq!(key("C").degree(note("A#")).to_string(), "#6");
+        assert_eq!(key("Eb").degree(note("Ab")).to_string(), "4");
+        assert_eq!(key("Am").degree(note("C")).to_string(), "3");
+        assert_eq!(key("Am").degree(note("G#")).to_string(), "#7");
+        assert_eq!(key("C").degree(note("Dbb")).to_string(), "1");
+
+        assert_eq!(key("C").note(ScaleDegree::new(7, Accidental::Flat)).to_string(), "Bb");
+        assert_eq!(key("D").note(ScaleDegree::new(7, Accidental::Natural)).to_string(), "C#");
+        assert_eq!(key("C").note(ScaleDegree::new(5, Accidental::Sharp)).to_string(), "G#");
+        assert_eq!(key("Em").note(ScaleDegree::new(3, Accidental::Natural)).to_string(), "G");
+        assert_eq!(key("Gb").note(ScaleDegree::new(4, Accidental::Natural)).to_string(), "Cb");
+    }
+
+    #[test]
+    fn signature() {
+        let key = |s| Key::from_str(s).unwrap();
+
+        assert_eq!(key("C").signature(), 0);
+        assert_eq!(key("Am").signature(), 0);
+        assert_eq!(key("Bb").signature(), -2);
+        assert_eq!(key("F#m").signature(), 3);
+        assert_eq!(key("D").transpose(1), key("Eb"));
+        assert_eq!(key("Em").transpose(1), key("Fm"));
+        assert_eq!(key("Am").transpose(1), key("Bbm"));
+        assert_eq!(key("C").transpose(-20), key("E"));
+        assert_eq!(key("C").transpose(i8::MIN), key("E"));
+        assert_eq!(key("E minor"), key("Em"));
+    }
+}
@@ -34,29 +34,85 @@ extern crate num_traits;
 #[macro_use] extern crate num_derive;
 
 pub mod chords;
+pub mod definitions;
 pub mod song;
+pub mod grid;
+pub mod key;
+pub mod selector;
+pub mod metadata;
 pub mod iterators;
-pub mod songparse;
+mod songparse;
+pub mod error;
 pub mod transpose;
+pub mod capo;
+pub mod analysis;
 pub mod format;
+pub mod writer;
+pub mod text;
 
 pub use {
     chords::{
+        Accidental,
+        Alteration,
         Chord,
-        Note
+        ChordQuality,
+        Extension,
+        Letter,
+        Note,
+        NoteNames,
+        SpelledNote
+    },
+    definitions::ChordDefinition,
+    error::{
+        Diagnostic,
+        ErrorKind,
+        ParseError,
+        Severity
+    },
+    grid::{
+        BarLine,
+        Grid,
+        GridCell,
+        GridRow,
+        GridShape,
+        Measure
+    },
+    key::{
+        Key,
+        Mode,
+        ScaleDegree
+    },
+    metadata::Metadata,
+    transpose::{
+        Pitch,
+        Transposer
+    },
+    capo::CapoSuggestion,
+    analysis::KeyCandidate,
+    selector::{
+        Condition,
+        Selector
     },
     song::{
         Song,
         Section,
+        Environment,
+        EnvironmentKind,
+        Tab,
         Paragraph,
         Line,
         Chunk
     },
     iterators::{
+        ChorusRecall,
+        Sections,
         SectionIterator,
         SectionMutIterator,
     },
     format::{
-        latin, Latin
-    }
+        german, latin, ChordStyle, German, Latin, Nashville, Notated, Notation, Roman, Styled,
+        Typographic
+    },
+    writer::ChordPro,
+    text::{PlainText, TextLine}
 };
\ No newline at end of file
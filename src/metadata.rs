@@ -0,0 +1,143 @@
+//! Contains the `Metadata` of a `Song`, filled from the ChordPro metadata
+//! directives (`{title}`, `{artist}`, `{key}`, `{meta}`...).
+//!
+use crate::chords::NoteNames;
+use crate::selector::Condition;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Song metadata.
+///
+/// Directives that may appear several times (like `{artist}`) keep all their
+/// values, in the order they appear in the song.
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// `{title}` (short: `t`)
+    pub title: String,
+    /// `{subtitle}` (short: `st`)
+    pub subtitles: Vec<String>,
+    /// `{artist}`
+    pub artists: Vec<String>,
+    /// `{composer}`
+    pub composers: Vec<String>,
+    /// `{lyricist}`
+    pub lyricists: Vec<String>,
+    /// `{copyright}`
+    pub copyright: Option<String>,
+    /// `{album}`
+    pub album: Option<String>,
+    /// `{year}`
+    pub year: Option<u16>,
+    /// `{key}`, as written. `Song::key` reads it with `names`.
+    pub key: Option<String>,
+    /// `{time}`
+    pub time: Option<String>,
+    /// `{tempo}`, in beats per minute
+    pub tempo: Option<u16>,
+    /// `{duration}`
+    pub duration: Option<String>,
+    /// `{capo}`
+    pub capo: u8,
+    /// Other `{meta: name value}` entries, by name
+    pub meta: BTreeMap<String, Vec<String>>,
+    /// Metadata directives with a selector, like `{title-guitar: ...}`, as
+    /// condition, name and value. They are applied by `Song::select`.
+    pub conditional: Vec<(Condition, String, String)>,
+    /// Note names the song was parsed with, that `key` is written in
+    pub names: NoteNames,
+}
+
+impl Metadata {
+    /// Sets the metadata item `name` to `value`, as `{name: value}` or
+    /// `{meta: name value}` would.
+    ///
+    /// Names that are not standard metadata, and standard items with a value
+    /// that is not valid (like `{year: unknown}`), are kept in `meta`.
+    pub fn insert(&mut self, name: &str, value: &str) {
+        if self.insert_standard(name, value) == Inserted::Unknown {
+            self.meta
+                .entry(name.to_owned())
+                .or_default()
+                .push(value.to_owned());
+        }
+    }
+
+    /// Sets a standard metadata item. Values that are not valid for the item
+    /// are kept in `meta`.
+    pub(crate) fn insert_standard(&mut self, name: &str, value: &str) -> Inserted {
+        let number = value.trim();
+        let value = value.to_owned();
+
+        match name {
+            "title" | "t" => self.title = value,
+            "subtitle" | "st" => self.subtitles.push(value),
+            "artist" => self.artists.push(value),
+            "composer" => self.composers.push(value),
+            "lyricist" => self.lyricists.push(value),
+            "copyright" => self.copyright = Some(value),
+            "album" => self.album = Some(value),
+            "year" => match number.parse() {
+                Ok(year) => self.year = Some(year),
+                Err(_) => return self.insert_invalid(name, value),
+            },
+            "key" => self.key = Some(value),
+            "time" => self.time = Some(value),
+            "tempo" => match number.parse() {
+                Ok(tempo) => self.tempo = Some(tempo),
+                Err(_) => return self.insert_invalid(name, value),
+            },
+            "duration" => self.duration = Some(value),
+            "capo" => match number.parse() {
+                Ok(capo) => self.capo = capo,
+                Err(_) => return self.insert_invalid(name, value),
+            },
+            _ => return Inserted::Unknown,
+        }
+
+        Inserted::Standard
+    }
+
+    fn insert_invalid(&mut self, name: &str, value: String) -> Inserted {
+        self.meta.entry(name.to_owned()).or_default().push(value);
+        Inserted::Invalid
+    }
+}
+
+/// How `Metadata::insert_standard` took an item
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Inserted {
+    Standard,
+    /// A standard item with a value that is not valid, kept in `meta`
+    Invalid,
+    /// Not a standard item
+    Unknown,
+}
+
+#[cfg(test)]
+mod test {
+    use super::Metadata;
+
+    #[test]
+    fn insert() {
+        let mut metadata = Metadata::default();
+        metadata.insert("t", "Title");
+        metadata.insert("artist", "First");
+        metadata.insert("artist", "Second");
+        metadata.insert("tempo", "120");
+        metadata.insert("year", "unknown");
+        metadata.insert("label", "Records");
+
+        assert_eq!(metadata.title, "Title");
+        assert_eq!(metadata.artists, vec!["First", "Second"]);
+        assert_eq!(metadata.tempo, Some(120));
+        assert_eq!(metadata.year, None);
+        assert_eq!(metadata.meta["year"], vec!["unknown"]);
+        assert_eq!(metadata.meta["label"], vec!["Records"]);
+
+        metadata.insert("capo", "2");
+        metadata.insert("capo", "high");
+        assert_eq!(metadata.capo, 2);
+        assert_eq!(metadata.meta["capo"], vec!["high"]);
+    }
+}
@@ -0,0 +1,158 @@
+//! Conditional directives, that only apply to some instruments or users.
+//!
+//! Any directive may be followed by a dash and a selector. The selector is
+//! negated with a `!`:
+//! ```text
+//! {title-guitar: Song for guitar}
+//! {start_of_chorus-ukulele}
+//! {comment-bass!: Not for the bass player}
+//! ```
+//! The parser keeps the selector of each directive and section, and
+//! `Song::select` gives the song as seen by an instrument or user.
+use crate::definitions::ChordDefinition;
+use crate::song::{Section, Song};
+use serde::Serialize;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Selector suffix of a directive, like `-guitar` or `-guitar!`
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct Condition {
+    /// Instrument type or user name
+    pub name: String,
+    /// Written with `!`: the directive applies to everyone else
+    pub negated: bool,
+}
+
+impl Condition {
+    /// Reads a selector suffix, with its dash. The negation may be written
+    /// before or after the name: `-!guitar` or `-guitar!`.
+    pub(crate) fn parse(suffix: &str) -> Self {
+        let name = suffix.trim_start_matches('-');
+        let negated = name.starts_with('!') || name.ends_with('!');
+
+        Condition {
+            name: name.trim_matches('!').to_owned(),
+            negated,
+        }
+    }
+
+    /// Whether the directive applies to `selector`
+    pub fn matches(&self, selector: &Selector) -> bool {
+        let selected = [&selector.instrument, &selector.user]
+            .iter()
+            .any(|s| s.as_deref() == Some(self.name.as_str()));
+
+        selected != self.negated
+    }
+}
+
+/// Writes the suffix, like `-guitar!`
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "-{}{}", self.name, if self.negated { "!" } else { "" })
+    }
+}
+
+/// Instrument and user a song is shown to
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Selector {
+    /// Instrument type, like `guitar` or `keyboard`
+    pub instrument: Option<String>,
+    /// User name
+    pub user: Option<String>,
+}
+
+impl Section {
+    /// The section as seen by `selector`, or `None` if it does not apply
+    fn select(&self, selector: &Selector) -> Option<Section> {
+        match self {
+            Section::Conditional(condition, section) if condition.matches(selector) => {
+                section.select(selector)
+            }
+            Section::Conditional(_, _) => None,
+            section => Some(section.clone()),
+        }
+    }
+}
+
+impl Song {
+    /// The song as seen by an instrument or user: conditional directives and
+    /// sections are kept only if they apply to `selector`, and lose their
+    /// condition. Conditional metadata overrides the unconditional values.
+    pub fn select(&self, selector: &Selector) -> Song {
+        let mut metadata = self.metadata.clone();
+        metadata.conditional.clear();
+        for (condition, name, value) in self.metadata.conditional.iter() {
+            if condition.matches(selector) {
+                metadata.insert(name, value);
+            }
+        }
+
+        let definitions = self
+            .definitions
+            .iter()
+            .filter(|d| d.condition.as_ref().map_or(true, |c| c.matches(selector)))
+            .map(|d| ChordDefinition {
+                condition: None,
+                ..d.clone()
+            })
+            .collect();
+
+        Song {
+            metadata,
+            definitions,
+            song: self.song.iter().filter_map(|s| s.select(selector)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn select() {
+        let song = Song::from_str(
+            "{title: Song}\n\
+             {title-guitar: Song for guitar}\n\
+             {define-guitar: C base-fret 1 frets x 3 2 0 1 0}\n\
+             {comment-bass!: Not for the bass}\n\
+             {start_of_chorus-keys}\n\
+             Chorus\n\
+             {end_of_chorus}\n",
+        )
+        .unwrap();
+
+        let guitar = song.select(&Selector {
+            instrument: Some("guitar".to_string()),
+            user: None,
+        });
+        assert_eq!(guitar.metadata.title, "Song for guitar");
+        assert_eq!(guitar.definitions.len(), 1);
+        assert_eq!(guitar.definitions[0].condition, None);
+        assert!(matches!(guitar.song[..], [Section::Comment(_)]));
+
+        let bass = song.select(&Selector {
+            instrument: Some("bass".to_string()),
+            user: Some("keys".to_string()),
+        });
+        assert_eq!(bass.metadata.title, "Song");
+        assert!(bass.definitions.is_empty());
+        assert!(matches!(bass.song[..], [Section::Chorus(_)]));
+    }
+
+    #[test]
+    fn parse_condition() {
+        assert_eq!(
+            Condition::parse("-!bass"),
+            Condition {
+                name: "bass".to_string(),
+                negated: true
+            }
+        );
+        assert_eq!(Condition::parse("-bass!").to_string(), "-bass!");
+        assert_eq!(Condition::parse("-bass").to_string(), "-bass");
+    }
+}
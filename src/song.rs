@@ -1,6 +1,10 @@
 //! Contains `Song` struct and its components.
 //!
 use crate::chords::Chord;
+use crate::definitions::ChordDefinition;
+use crate::grid::Grid;
+use crate::metadata::Metadata;
+use crate::selector::Condition;
 use serde::Serialize;
 
 /// Chunk of lyrics or a chord
@@ -22,6 +26,56 @@ pub struct Line(pub Vec<Chunk>);
 #[derive(Serialize, Debug, Default, PartialEq, Clone)]
 pub struct Paragraph(pub Vec<Line>);
 
+/// Lyrics inside `{start_of_X}` and `{end_of_X}` directives
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+pub struct Environment {
+    /// Label given in the start directive, like `{start_of_chorus: Chorus 2}`
+    pub label: Option<String>,
+    pub content: Paragraph,
+}
+
+impl From<Paragraph> for Environment {
+    fn from(content: Paragraph) -> Self {
+        Environment {
+            label: None,
+            content,
+        }
+    }
+}
+
+/// Kind of an explicit environment other than the chorus
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum EnvironmentKind {
+    /// `{start_of_verse}` (short: `sov`)
+    Verse,
+    /// `{start_of_bridge}` (short: `sob`)
+    Bridge,
+    /// Any other `{start_of_X}`, with its name `X`
+    Custom(String),
+}
+
+impl EnvironmentKind {
+    /// Name of the environment, as in `{start_of_name}`
+    pub fn name(&self) -> &str {
+        match self {
+            EnvironmentKind::Verse => "verse",
+            EnvironmentKind::Bridge => "bridge",
+            EnvironmentKind::Custom(name) => name,
+        }
+    }
+}
+
+/// Tablature between `{start_of_tab}` and `{end_of_tab}`
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+pub struct Tab {
+    /// Label given in the start directive
+    pub label: Option<String>,
+    /// Lines of the tablature, exactly as written
+    pub lines: Vec<String>,
+}
+
 /// A song section (chorus, verse or a comment)
 #[derive(Serialize, Debug, PartialEq, Clone)]
 #[serde(tag = "class", content = "content")]
@@ -29,22 +83,42 @@ pub struct Paragraph(pub Vec<Line>);
 #[non_exhaustive]
 pub enum Section {
     /// The chorus
-    Chorus(Paragraph),
+    Chorus(Environment),
 
-    /// A verse
+    /// A verse, as a paragraph without any environment directive
     Verse(Paragraph),
 
     /// Chordpro comment.
     /// Only contains one Line.
     Comment(Line),
+
+    /// An explicit environment, like a `{start_of_bridge}`
+    Environment(EnvironmentKind, Environment),
+
+    /// Tablature (`{start_of_tab}`), preformatted text without chords
+    Tab(Tab),
+
+    /// Chord grid (`{start_of_grid}`)
+    Grid(Grid),
+
+    /// Recall of the previous chorus (`{chorus}`), with an optional label
+    ChorusRef(Option<String>),
+
+    /// A `{transpose}` directive: the chords after it are transposed by
+    /// this many semitones
+    Transpose(i8),
+
+    /// A section that only applies to some instruments or users, like
+    /// `{start_of_chorus-guitar}`
+    Conditional(Condition, Box<Section>),
 }
 
 /// A song with its chords
 #[derive(Serialize, Debug, Default, PartialEq, Clone)]
 #[non_exhaustive]
 pub struct Song {
-    pub title: String,
-    pub artist: String,
-    pub capo: u8,
+    pub metadata: Metadata,
+    /// Chords defined with `{define}` or `{chord}`
+    pub definitions: Vec<ChordDefinition>,
     pub song: Vec<Section>,
 }
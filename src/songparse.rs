@@ -3,13 +3,84 @@ use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 use std::str::FromStr;
 
-use crate::song::{Chunk, Line, Paragraph, Section, Song};
+use crate::definitions::ChordDefinition;
+use crate::error::{Diagnostic, ErrorKind, ParseError, Severity};
+use crate::key::{Key, Mode, ScaleDegree};
+use crate::grid::{BarLine, Grid, GridCell, GridRow, GridShape, Measure};
+use crate::metadata::{Inserted, Metadata};
+use crate::selector::Condition;
+use crate::song::{Chunk, Environment, EnvironmentKind, Line, Paragraph, Section, Song, Tab};
 
-use crate::chords::{Chord, Note};
+use crate::chords::{Accidental, Alteration, Chord, ChordQuality, Extension, Letter, Note, NoteNames, SpelledNote};
 
 #[derive(Parser)]
 #[grammar = "grammar/chordpro.pest"]
-pub struct SongParser;
+struct SongParser;
+
+/// State kept while building a song from the parse tree
+struct Context<'a> {
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+    /// Key of the song so far, for chords written relative to it
+    key: Option<Key>,
+    names: NoteNames,
+    /// Note names of the first chord of the song, with `NoteNames::Auto`
+    detected: Option<NoteNames>,
+}
+
+impl<'a> Context<'a> {
+    fn new(source: &'a str) -> Self {
+        Context {
+            source,
+            diagnostics: Vec::new(),
+            key: None,
+            names: NoteNames::Auto,
+            detected: None,
+        }
+    }
+
+    fn report(&mut self, severity: Severity, error: ParseError) {
+        self.diagnostics.push(Diagnostic { severity, error });
+    }
+
+    fn error(&mut self, kind: ErrorKind, span: pest::Span<'a>) {
+        self.error_at(kind, span.start(), span.end());
+    }
+
+    /// Reports an error between two byte offsets of the source
+    fn error_at(&mut self, kind: ErrorKind, start: usize, end: usize) {
+        let error = ParseError::new(kind, self.source, start, end);
+        self.report(Severity::Error, error);
+    }
+
+    fn warning(&mut self, kind: ErrorKind, span: pest::Span<'a>) {
+        self.warning_at(kind, span.start(), span.end());
+    }
+
+    /// Reports a warning between two byte offsets of the source
+    fn warning_at(&mut self, kind: ErrorKind, start: usize, end: usize) {
+        let error = ParseError::new(kind, self.source, start, end);
+        self.report(Severity::Warning, error);
+    }
+
+    /// Byte offset of `text`, a slice of the source
+    fn offset(&self, text: &str) -> usize {
+        text.as_ptr() as usize - self.source.as_ptr() as usize
+    }
+
+    /// Returns `value`, or the first error found while building it
+    fn finish<T>(self, value: T) -> Result<T, ParseError> {
+        let error = self
+            .diagnostics
+            .into_iter()
+            .find(|d| d.severity == Severity::Error);
+
+        match error {
+            Some(d) => Err(d.error),
+            None => Ok(value),
+        }
+    }
+}
 
 trait HasRule {
     const MATCH_RULE: Rule;
@@ -17,121 +88,471 @@ trait HasRule {
 
 trait ProcessChild<'a> {
     //Match and call from_pair
-    fn process_child<'m>(&'m mut self, pair: Pair<'a, Rule>);
+    fn process_child<'m>(&'m mut self, pair: Pair<'a, Rule>, ctx: &mut Context<'a>);
 }
 
 trait FromPair<'a>: HasRule + Sized {
-    fn from_pair(content: Pair<'a, Rule>) -> Self;
+    fn from_pair(content: Pair<'a, Rule>, ctx: &mut Context<'a>) -> Self;
 }
 
 impl<'a, T> FromPair<'a> for T
 where
     T: Default + ProcessChild<'a> + HasRule,
 {
-    fn from_pair(content: Pair<'a, Rule>) -> Self {
+    fn from_pair(content: Pair<'a, Rule>, ctx: &mut Context<'a>) -> Self {
         let mut d = Self::default();
 
         for input in content.into_inner() {
-            d.process_child(input);
+            d.process_child(input, ctx);
         }
 
         d
     }
 }
 
+/// Builds the error for the invalid input between `start` and `end`,
+/// guessing what went wrong from its contents.
+fn syntax_error(source: &str, start: usize, end: usize) -> ParseError {
+    let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = source[start..]
+        .find('\n')
+        .map(|i| start + i)
+        .unwrap_or(source.len());
+    let line = &source[line_start..line_end];
+
+    // A `{` left open in this line
+    if let Some(open) = line.rfind('{') {
+        if !line[open..].contains('}') {
+            return ParseError::new(
+                ErrorKind::UnterminatedDirective,
+                source,
+                line_start + open,
+                line_end,
+            );
+        }
+    }
+
+    ParseError::new(ErrorKind::Syntax, source, start, end)
+}
+
+/// Converts a pest error into a `ParseError`
+fn pest_error(source: &str, error: pest::error::Error<Rule>) -> ParseError {
+    let (start, end) = match error.location {
+        pest::error::InputLocation::Pos(p) => (p, p),
+        pest::error::InputLocation::Span(span) => span,
+    };
+
+    syntax_error(source, start, end)
+}
+
+/// Parses the whole `source` as a single `T`, failing with `kind` if it is not valid
+fn parse_single<'a, T: FromPair<'a>>(
+    rule: Rule,
+    kind: ErrorKind,
+    source: &'a str,
+) -> Result<T, ParseError> {
+    let content = SongParser::parse(rule, source)
+        .map_err(|_| ParseError::new(kind, source, 0, source.len()))?;
+
+    let mut ctx = Context::new(source);
+    let value = T::from_pair(content.peek().unwrap(), &mut ctx);
+    ctx.finish(value)
+}
+
+impl Song {
+    /// Parses a song, recovering from errors instead of failing.
+    ///
+    /// Invalid chords are kept as lyrics with their brackets, choruses that
+    /// are not closed end where the next section starts and invalid lines
+    /// are skipped. Every problem found is returned as a `Diagnostic`;
+    /// `Song::from_str` would fail if any of them is an error.
+    pub fn parse_lenient(s: &str) -> (Song, Vec<Diagnostic>) {
+        let mut ctx = Context::new(s);
+
+        let song = match SongParser::parse(Song::MATCH_RULE, s) {
+            Ok(content) => Song::from_pair(content.peek().unwrap(), &mut ctx),
+            Err(e) => {
+                ctx.report(Severity::Error, pest_error(s, e));
+                Song::default()
+            }
+        };
+
+        (song, ctx.diagnostics)
+    }
+}
+
+impl Song {
+    /// Parses a song whose chords use `names` for their notes. `Song::from_str`
+    /// detects them.
+    pub fn parse_with(s: &str, names: NoteNames) -> Result<Song, ParseError> {
+        let content = SongParser::parse(Song::MATCH_RULE, s).map_err(|e| pest_error(s, e))?;
+
+        let mut ctx = Context::new(s);
+        ctx.names = names;
+        let mut song = Song::from_pair(content.peek().unwrap(), &mut ctx);
+        song.metadata.names = names;
+        ctx.finish(song)
+    }
+}
+
 impl FromStr for Song {
-    type Err = pest::error::Error<Rule>;
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Song, Self::Err> {
-        let content = SongParser::parse(Song::MATCH_RULE, s)?;
+        Song::parse_with(s, NoteNames::Auto)
+    }
+}
 
-        let pair = content.peek().unwrap();
-        Ok(Song::from_pair(pair))
+impl Chord {
+    /// Parses a chord whose notes use `names`
+    pub fn parse_with(s: &str, names: NoteNames) -> Result<Chord, ParseError> {
+        let mut ctx = Context::new(s);
+        ctx.names = names;
+
+        match parse_absolute(s, &mut ctx) {
+            Some((_, chord)) => ctx.finish(chord),
+            None => Err(ParseError::new(ErrorKind::MalformedChord, s, 0, s.len())),
+        }
     }
 }
 
 impl FromStr for Chord {
-    type Err = pest::error::Error<Rule>;
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Chord, Self::Err> {
-        let content = SongParser::parse(Chord::MATCH_RULE, s)?;
+        Chord::parse_with(s, NoteNames::Auto)
+    }
+}
+
+impl FromStr for SpelledNote {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<SpelledNote, Self::Err> {
+        parse_single(Rule::note_input, ErrorKind::MalformedNote, s)
+    }
+}
+
+impl Key {
+    /// Parses a key whose tonic uses `names`. Letter and latin names are
+    /// both accepted unless `names` is `NoteNames::German`.
+    pub fn parse_with(s: &str, names: NoteNames) -> Result<Key, ParseError> {
+        let rule = match names {
+            NoteNames::German => Rule::german_key_input,
+            _ => Rule::key_input,
+        };
 
-        let pair = content.peek().unwrap();
-        Ok(Chord::from_pair(pair))
+        parse_single(rule, ErrorKind::MalformedKey, s.trim())
+    }
+}
+
+impl FromStr for Key {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Key, Self::Err> {
+        Key::parse_with(s, NoteNames::Auto)
+    }
+}
+
+impl Chord {
+    /// Parses a chord relative to `key`, written as a Nashville number
+    /// (`6m`, `5/7`) or a Roman numeral (`vi`, `V/vii`)
+    pub fn from_relative(s: &str, key: Key) -> Result<Chord, ParseError> {
+        parse_single(Rule::relative_chord_input, ErrorKind::MalformedChord, s)
+            .map(|c: RelativeChord| c.resolve(key))
     }
 }
 
 impl FromStr for Note {
-    type Err = pest::error::Error<Rule>;
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Note, Self::Err> {
-        let content = SongParser::parse(Note::MATCH_RULE, s)?;
-
-        let pair = content.peek().unwrap();
-        Ok(Note::from_pair(pair))
+        SpelledNote::from_str(s).map(|n| n.pitch_class())
     }
 }
 
-impl HasRule for Note {
+impl HasRule for SpelledNote {
     const MATCH_RULE: Rule = Rule::note;
 }
 
-impl<'a> ProcessChild<'a> for Note {
-    fn process_child(&mut self, pair: Pair<'a, Rule>) {
+impl<'a> ProcessChild<'a> for SpelledNote {
+    fn process_child(&mut self, pair: Pair<'a, Rule>, _ctx: &mut Context<'a>) {
         match pair.as_rule() {
-            Rule::note_s => {
-                let k: Option<char> = pair.as_str().chars().nth(0).map(|c| c.to_ascii_uppercase());
+            Rule::note_s | Rule::german_note_s => {
+                let k: Option<char> = pair.as_str().chars().next().map(|c| c.to_ascii_uppercase());
+
+                // In German, B is B flat and H is B
+                if pair.as_rule() == Rule::german_note_s && k == Some('B') {
+                    self.accidental = Accidental::Flat;
+                }
 
                 match k {
-                    Some('A') => *self = Note::A,
-                    Some('B') => *self = Note::B,
-                    Some('C') => *self = Note::C,
-                    Some('D') => *self = Note::D,
-                    Some('E') => *self = Note::E,
-                    Some('F') => *self = Note::F,
-                    Some('G') => *self = Note::G,
+                    Some('A') => self.letter = Letter::A,
+                    Some('B') | Some('H') => self.letter = Letter::B,
+                    Some('C') => self.letter = Letter::C,
+                    Some('D') => self.letter = Letter::D,
+                    Some('E') => self.letter = Letter::E,
+                    Some('F') => self.letter = Letter::F,
+                    Some('G') => self.letter = Letter::G,
                     _ => {}
                 }
             }
 
-            Rule::sharp => {
-                *self = *self + 1;
+            Rule::latin_note_s => {
+                self.letter = match pair.as_str().to_ascii_lowercase().as_str() {
+                    "do" | "ut" => Letter::C,
+                    "re" => Letter::D,
+                    "mi" => Letter::E,
+                    "fa" => Letter::F,
+                    "sol" => Letter::G,
+                    "la" => Letter::A,
+                    _ => Letter::B,
+                }
             }
-            Rule::flat => {
-                *self = *self - 1;
+
+            Rule::sharp | Rule::flat | Rule::double_sharp | Rule::double_flat => {
+                let step = match pair.as_rule() {
+                    Rule::sharp => 1,
+                    Rule::flat => -1,
+                    Rule::double_sharp => 2,
+                    _ => -2,
+                };
+                self.accidental = Accidental::from_semitones(self.accidental.semitones() + step)
+                    .unwrap_or(self.accidental);
             }
             _ => {}
         }
     }
 }
 
+impl HasRule for Key {
+    const MATCH_RULE: Rule = Rule::key;
+}
+
+impl<'a> ProcessChild<'a> for Key {
+    fn process_child(&mut self, pair: Pair<'a, Rule>, ctx: &mut Context<'a>) {
+        match pair.as_rule() {
+            Rule::note | Rule::latin_note | Rule::german_note => self.tonic = SpelledNote::from_pair(pair, ctx),
+            Rule::key_major => self.mode = Mode::Major,
+            Rule::key_minor => self.mode = Mode::Minor,
+            _ => {}
+        }
+    }
+}
+
 impl HasRule for Chord {
     const MATCH_RULE: Rule = Rule::chord;
 }
 
 impl<'a> ProcessChild<'a> for Chord {
-    fn process_child(&mut self, pair: Pair<'a, Rule>) {
+    fn process_child(&mut self, pair: Pair<'a, Rule>, ctx: &mut Context<'a>) {
         match pair.as_rule() {
-            Rule::note => {
-                let note = Note::from_pair(pair);
+            Rule::note | Rule::latin_note | Rule::german_note => {
+                let note = SpelledNote::from_pair(pair, ctx);
                 self.root = note;
                 self.bass = note;
             }
-            Rule::major => {
-                self.minor = false;
+            Rule::major => self.quality = ChordQuality::Major,
+            Rule::minor => self.quality = ChordQuality::Minor,
+            Rule::augmented => self.quality = ChordQuality::Augmented,
+            Rule::diminished => self.quality = ChordQuality::Diminished,
+            Rule::half_diminished => self.quality = ChordQuality::HalfDiminished,
+            Rule::sus => {
+                self.quality = match pair.as_str() {
+                    "sus2" => ChordQuality::Suspended2,
+                    _ => ChordQuality::Suspended4,
+                }
+            }
+            Rule::major_seventh => {
+                let number = pair.into_inner().next().map(|n| parse_number(&n)).unwrap_or(7);
+                self.extensions.push(Extension::Major(number));
+            }
+            Rule::degree => {
+                let (alteration, number) = parse_degree(pair);
+                self.extensions.push(Extension::Degree(alteration, number));
             }
-            Rule::minor => {
-                self.minor = true;
+            Rule::add => {
+                let (alteration, number) = parse_degree(pair.into_inner().next().unwrap());
+                self.extensions.push(Extension::Add(alteration, number));
+            }
+            Rule::omit => {
+                let number = parse_number(&pair.into_inner().next().unwrap());
+                self.extensions.push(Extension::Omit(number));
+            }
+            Rule::six_nine => {
+                self.extensions.push(Extension::Degree(None, 6));
+                self.extensions.push(Extension::Add(None, 9));
+            }
+            Rule::bass | Rule::latin_bass | Rule::german_bass => {
+                self.bass = SpelledNote::from_pair(pair.into_inner().peek().unwrap(), ctx);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl HasRule for ScaleDegree {
+    const MATCH_RULE: Rule = Rule::scale_degree;
+}
+
+impl<'a> ProcessChild<'a> for ScaleDegree {
+    fn process_child(&mut self, pair: Pair<'a, Rule>, _ctx: &mut Context<'a>) {
+        match pair.as_rule() {
+            Rule::sharp => self.accidental = Accidental::Sharp,
+            Rule::flat => self.accidental = Accidental::Flat,
+            Rule::nashville => self.degree = parse_number(&pair),
+            Rule::roman_upper | Rule::roman_lower => {
+                self.degree = match pair.as_str().to_ascii_lowercase().as_str() {
+                    "i" => 1,
+                    "ii" => 2,
+                    "iii" => 3,
+                    "iv" => 4,
+                    "v" => 5,
+                    "vi" => 6,
+                    _ => 7,
+                }
             }
-            Rule::symbol => {
-                self.others = pair.as_str().to_owned();
+            _ => {}
+        }
+    }
+}
+
+/// Bass of a relative chord: a degree, or a note that does not depend on
+/// the key
+enum RelativeBass {
+    Degree(ScaleDegree),
+    Note(SpelledNote),
+}
+
+/// A chord relative to a key, before the key is known
+#[derive(Default)]
+struct RelativeChord {
+    root: ScaleDegree,
+    /// Written as a lower case Roman numeral, that is minor by default
+    lower_case: bool,
+    /// Written with its quality, like `m`, `°` or `sus4`
+    quality: bool,
+    /// Quality and extensions of the chord
+    chord: Chord,
+    bass: Option<RelativeBass>,
+}
+
+impl RelativeChord {
+    fn resolve(self, key: Key) -> Chord {
+        let root = key.note(self.root);
+        let mut chord = Chord {
+            root,
+            bass: root,
+            ..self.chord
+        };
+
+        if self.lower_case && !self.quality {
+            chord.quality = ChordQuality::Minor;
+        }
+        match self.bass {
+            Some(RelativeBass::Degree(degree)) => chord.bass = key.note(degree),
+            Some(RelativeBass::Note(note)) => chord.bass = note,
+            None => {}
+        }
+
+        chord.normalized()
+    }
+}
+
+impl HasRule for RelativeChord {
+    const MATCH_RULE: Rule = Rule::relative_chord;
+}
+
+impl<'a> ProcessChild<'a> for RelativeChord {
+    fn process_child(&mut self, pair: Pair<'a, Rule>, ctx: &mut Context<'a>) {
+        match pair.as_rule() {
+            Rule::scale_degree => {
+                self.lower_case = pair.clone().into_inner().any(|p| p.as_rule() == Rule::roman_lower);
+                self.root = ScaleDegree::from_pair(pair, ctx);
             }
-            Rule::number => {
-                self.number = u8::from_str(pair.as_str()).unwrap();
+            Rule::relative_bass => {
+                let bass = pair.into_inner().next().unwrap();
+                self.bass = Some(match bass.as_rule() {
+                    Rule::scale_degree => RelativeBass::Degree(ScaleDegree::from_pair(bass, ctx)),
+                    _ => RelativeBass::Note(SpelledNote::from_pair(bass, ctx)),
+                });
             }
-            Rule::bass => {
-                self.bass = Note::from_pair(pair.into_inner().peek().unwrap());
+            Rule::major
+            | Rule::minor
+            | Rule::augmented
+            | Rule::diminished
+            | Rule::half_diminished
+            | Rule::sus => {
+                self.quality = true;
+                self.chord.process_child(pair, ctx);
             }
+            _ => self.chord.process_child(pair, ctx),
+        }
+    }
+}
+
+fn parse_number(pair: &Pair<Rule>) -> u8 {
+    u8::from_str(pair.as_str()).unwrap()
+}
+
+/// Reads a chord degree, like `7` or `b9`
+fn parse_degree(pair: Pair<Rule>) -> (Option<Alteration>, u8) {
+    let mut alteration = None;
+    let mut number = 0;
+
+    for child in pair.into_inner() {
+        match child.as_rule() {
+            Rule::sharp => alteration = Some(Alteration::Sharp),
+            Rule::flat => alteration = Some(Alteration::Flat),
+            Rule::number => number = parse_number(&child),
             _ => {}
         }
     }
+
+    (alteration, number)
+}
+
+/// Parses a chord with the note names of the context, and tells which
+/// names it is written with
+fn parse_absolute<'a>(text: &'a str, ctx: &mut Context<'a>) -> Option<(NoteNames, Chord)> {
+    let rules: &[(NoteNames, Rule)] = match ctx.names {
+        NoteNames::Auto => &[
+            (NoteNames::Letters, Rule::chord_input),
+            (NoteNames::Latin, Rule::latin_chord_input),
+        ],
+        NoteNames::Letters => &[(NoteNames::Letters, Rule::chord_input)],
+        NoteNames::Latin => &[(NoteNames::Latin, Rule::latin_chord_input)],
+        NoteNames::German => &[(NoteNames::German, Rule::german_chord_input)],
+    };
+
+    rules.iter().find_map(|&(names, rule)| {
+        let mut content = SongParser::parse(rule, text).ok()?;
+        Some((names, Chord::from_pair(content.next().unwrap(), ctx).normalized()))
+    })
+}
+
+/// Parses a chord relative to the `{key}` found before it in the song
+fn parse_relative<'a>(text: &'a str, ctx: &mut Context<'a>) -> Option<Chord> {
+    let key = ctx.key?;
+    let mut content = SongParser::parse(Rule::relative_chord_input, text).ok()?;
+    Some(RelativeChord::from_pair(content.next().unwrap(), ctx).resolve(key))
+}
+
+/// Parses the text of a chord found inside a song. Chords relative to the
+/// key are read if the song has a `{key}` before them.
+///
+/// With `NoteNames::Auto`, the first chord sets the note names of the
+/// song, and the chords written with the other names are reported.
+fn parse_chord<'a>(text: &'a str, ctx: &mut Context<'a>) -> Option<Chord> {
+    let (names, chord) = match parse_absolute(text, ctx) {
+        Some(chord) => chord,
+        None => return parse_relative(text, ctx),
+    };
+
+    match ctx.detected {
+        None => ctx.detected = Some(names),
+        Some(detected) if detected != names => {
+            let start = ctx.offset(text);
+            ctx.warning_at(ErrorKind::MixedNoteNames, start, start + text.len());
+        }
+        Some(_) => {}
+    }
+
+    Some(chord)
 }
 
 impl HasRule for Line {
@@ -139,14 +560,26 @@ impl HasRule for Line {
 }
 
 impl<'a> ProcessChild<'a> for Line {
-    fn process_child(&mut self, pair: Pair<'a, Rule>) {
+    fn process_child(&mut self, pair: Pair<'a, Rule>, ctx: &mut Context<'a>) {
         match pair.as_rule() {
-            Rule::chord => {
-                self.0.push(Chunk::Chord(Chord::from_pair(pair)));
-            }
+            Rule::chord_text => match parse_chord(pair.as_str(), ctx) {
+                Some(chord) => self.0.push(Chunk::Chord(chord)),
+                None => {
+                    ctx.error(ErrorKind::MalformedChord, pair.as_span());
+                    self.0.push(Chunk::Lyrics(format!("[{}]", pair.as_str())));
+                }
+            },
             Rule::text => {
                 self.0.push(Chunk::Lyrics(pair.as_str().to_owned()));
             }
+            Rule::unterminated_chord => {
+                ctx.error(ErrorKind::UnterminatedChord, pair.as_span());
+                self.0.push(Chunk::Lyrics(pair.as_str().to_owned()));
+            }
+            Rule::stray_bracket => {
+                ctx.error(ErrorKind::StrayBracket, pair.as_span());
+                self.0.push(Chunk::Lyrics(pair.as_str().to_owned()));
+            }
             _ => {}
         }
     }
@@ -157,9 +590,9 @@ impl HasRule for Paragraph {
 }
 
 impl<'a> ProcessChild<'a> for Paragraph {
-    fn process_child(&mut self, pair: Pair<'a, Rule>) {
+    fn process_child(&mut self, pair: Pair<'a, Rule>, ctx: &mut Context<'a>) {
         if let Rule::line = pair.as_rule() {
-            self.0.push(Line::from_pair(pair));
+            self.0.push(Line::from_pair(pair, ctx));
         }
     }
 }
@@ -169,49 +602,438 @@ impl HasRule for Section {
 }
 
 impl<'a> FromPair<'a> for Section {
-    fn from_pair(content: Pair<'a, Rule>) -> Self {
+    fn from_pair(content: Pair<'a, Rule>, ctx: &mut Context<'a>) -> Self {
         let pair = content.into_inner().peek().unwrap();
         let rule = pair.as_rule();
+        // The selector of environments is in their start directive
+        let directive = match pair.clone().into_inner().next() {
+            Some(start) if matches!(start.as_rule(), Rule::start_env | Rule::start_tab | Rule::start_grid) => start,
+            _ => pair.clone(),
+        };
+        let condition = directive
+            .into_inner()
+            .find(|p| p.as_rule() == Rule::selector)
+            .map(|selector| Condition::parse(selector.as_str()));
 
-        match rule {
-            Rule::paragraph => Section::Verse(Paragraph::from_pair(pair)),
-            Rule::chorus => {
-                Section::Chorus(Paragraph::from_pair(pair.into_inner().peek().unwrap()))
-            }
-            Rule::comment => Section::Comment(Line::from_pair(pair.into_inner().peek().unwrap())),
+        let section = match rule {
+            Rule::paragraph => Section::Verse(Paragraph::from_pair(pair, ctx)),
+            Rule::environment => Section::from_environment(pair, ctx),
+            Rule::tab => Section::from_tab(pair, ctx),
+            Rule::grid => Section::from_grid(pair, ctx),
+            Rule::chorus_ref => Section::ChorusRef(
+                pair.into_inner()
+                    .find(|p| p.as_rule() == Rule::args)
+                    .and_then(|args| parse_label(args.as_str())),
+            ),
+            Rule::transpose => Section::Transpose(
+                match pair.into_inner().find(|p| p.as_rule() == Rule::args) {
+                    Some(args) => i8::from_str(args.as_str().trim()).unwrap_or_else(|_| {
+                        ctx.error(ErrorKind::MalformedTranspose, args.as_span());
+                        0
+                    }),
+                    None => 0,
+                },
+            ),
+            Rule::comment => Section::Comment(
+                pair.into_inner()
+                    .find(|p| p.as_rule() == Rule::args_chords)
+                    .map(|args| Line::from_pair(args, ctx))
+                    .unwrap_or_default(),
+            ),
             _ => Section::Comment(Line::default()),
+        };
+
+        match condition {
+            Some(condition) => Section::Conditional(condition, Box::new(section)),
+            None => section,
+        }
+    }
+}
+
+/// Name of the environment started or ended by a directive, and the label
+/// given to it
+fn environment_name<'a>(directive: Pair<'a, Rule>) -> (&'a str, Option<String>) {
+    let mut name = "";
+    let mut label = None;
+
+    for child in directive.into_inner() {
+        match child.as_rule() {
+            Rule::env_name => name = child.as_str(),
+            Rule::env_short | Rule::env_short_end => {
+                name = match &child.as_str()[1..] {
+                    "oc" => "chorus",
+                    "ov" => "verse",
+                    _ => "bridge",
+                }
+            }
+            Rule::args => label = parse_label(child.as_str()),
+            _ => {}
+        }
+    }
+
+    (name, label)
+}
+
+/// Finds the value of the attribute `name` in directive arguments
+/// like `shape="1+4x2+4" label="Intro"`
+fn attribute<'a>(args: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = args.trim_start();
+
+    while let Some((key, value)) = rest.split_once('=') {
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+        };
+
+        if key.trim() == name {
+            return Some(value);
+        }
+        rest = next.trim_start();
+    }
+
+    None
+}
+
+/// Reads the label of an environment, given as `Label` or `label="Label"`
+fn parse_label(args: &str) -> Option<String> {
+    let label = match attribute(args, "label") {
+        Some(label) => label,
+        None if args.contains('=') => "",
+        None => args.trim(),
+    };
+
+    if label.is_empty() {
+        None
+    } else {
+        Some(label.to_owned())
+    }
+}
+
+impl Section {
+    /// Builds the section for an environment, checking that it is closed
+    fn from_environment<'a>(pair: Pair<'a, Rule>, ctx: &mut Context<'a>) -> Self {
+        let mut environment = Environment::default();
+        let mut start = None;
+        let mut name = "";
+        let mut end = None;
+
+        for child in pair.into_inner() {
+            match child.as_rule() {
+                Rule::start_env => {
+                    start = Some(child.as_span());
+                    (name, environment.label) = environment_name(child);
+                }
+                Rule::end_env => end = Some(environment_name(child).0),
+                Rule::paragraph => environment
+                    .content
+                    .0
+                    .extend(Paragraph::from_pair(child, ctx).0),
+                _ => {}
+            }
+        }
+
+        if let (Some(start), true) = (start, end != Some(name)) {
+            let kind = match name {
+                "chorus" => ErrorKind::UnterminatedChorus,
+                _ => ErrorKind::UnterminatedEnvironment,
+            };
+            ctx.error(kind, start);
+        }
+
+        match name {
+            "chorus" => Section::Chorus(environment),
+            "verse" => Section::Environment(EnvironmentKind::Verse, environment),
+            "bridge" => Section::Environment(EnvironmentKind::Bridge, environment),
+            custom => Section::Environment(EnvironmentKind::Custom(custom.to_owned()), environment),
         }
     }
 }
 
+impl Section {
+    /// Builds the section for a tablature, checking that it is closed
+    fn from_tab<'a>(pair: Pair<'a, Rule>, ctx: &mut Context<'a>) -> Self {
+        let mut tab = Tab::default();
+        let mut start = None;
+        let mut terminated = false;
+
+        for child in pair.into_inner() {
+            match child.as_rule() {
+                Rule::start_tab => {
+                    start = Some(child.as_span());
+                    tab.label = child
+                        .into_inner()
+                        .find(|p| p.as_rule() == Rule::args)
+                        .and_then(|args| parse_label(args.as_str()));
+                }
+                Rule::tab_line => tab.lines.push(child.as_str().to_owned()),
+                Rule::tab_end => terminated = true,
+                _ => {}
+            }
+        }
+
+        if let (Some(start), false) = (start, terminated) {
+            ctx.error(ErrorKind::UnterminatedEnvironment, start);
+        }
+
+        Section::Tab(tab)
+    }
+}
+
+/// Reads a grid shape, like `4x4`, `16` or `1+4x2+4`
+fn parse_shape(shape: &str) -> Option<GridShape> {
+    let parts: Vec<&str> = shape.trim().split('+').collect();
+    let (margin, cells, comment) = match parts[..] {
+        [cells] => ("0", cells, "0"),
+        [margin, cells, comment] => (margin, cells, comment),
+        _ => return None,
+    };
+    let (measures, beats) = cells.split_once('x').unwrap_or(("1", cells));
+
+    Some(GridShape {
+        margin: margin.parse().ok()?,
+        measures: measures.parse().ok()?,
+        beats: beats.parse().ok()?,
+        comment: comment.parse().ok()?,
+    })
+}
+
+fn parse_bar(token: &str) -> Option<BarLine> {
+    match token {
+        "|" => Some(BarLine::Single),
+        "||" => Some(BarLine::Double),
+        "|." => Some(BarLine::End),
+        "|:" => Some(BarLine::RepeatStart),
+        "||:" => Some(BarLine::DoubleRepeatStart),
+        ":|" => Some(BarLine::RepeatEnd),
+        ":||" => Some(BarLine::DoubleRepeatEnd),
+        ":|:" => Some(BarLine::RepeatBoth),
+        _ => match token.strip_prefix(':') {
+            Some(volta) => volta.strip_prefix('|')?.parse().ok().map(BarLine::RepeatEndVolta),
+            None => token.strip_prefix('|')?.parse().ok().map(BarLine::Volta),
+        },
+    }
+}
+
+impl GridCell {
+    /// Reads a cell that starts at byte `offset` of the source
+    fn parse<'a>(token: &'a str, offset: usize, ctx: &mut Context<'a>) -> Self {
+        let cell = match token {
+            "." => Some(GridCell::Continue),
+            "%" => Some(GridCell::RepeatMeasure),
+            "%%" => Some(GridCell::RepeatTwoMeasures),
+            split if split.contains('~') => split
+                .split('~')
+                .map(|c| parse_chord(c, ctx))
+                .collect::<Option<_>>()
+                .map(GridCell::Split),
+            chord => parse_chord(chord, ctx).map(GridCell::Chord),
+        };
+
+        cell.unwrap_or_else(|| {
+            ctx.error_at(ErrorKind::MalformedChord, offset, offset + token.len());
+            GridCell::Text(token.to_owned())
+        })
+    }
+}
+
+impl GridRow {
+    fn from_line<'a>(line: Pair<'a, Rule>, ctx: &mut Context<'a>) -> Self {
+        let offset = line.as_span().start();
+        let text = line.as_str();
+        let tokens: Vec<(usize, &str)> = text
+            .split_whitespace()
+            .map(|token| (offset + token.as_ptr() as usize - text.as_ptr() as usize, token))
+            .collect();
+        let bars: Vec<Option<BarLine>> = tokens.iter().map(|(_, t)| parse_bar(t)).collect();
+        let join = |tokens: &[(usize, &str)]| {
+            let words: Vec<&str> = tokens.iter().map(|(_, t)| *t).collect();
+            Some(words.join(" ")).filter(|s| !s.is_empty())
+        };
+
+        let (first, last) = match (
+            bars.iter().position(Option::is_some),
+            bars.iter().rposition(Option::is_some),
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                return GridRow {
+                    margin: join(&tokens),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let mut row = GridRow {
+            margin: join(&tokens[..first]),
+            start: bars[first],
+            measures: Vec::new(),
+            comment: join(&tokens[last + 1..]),
+        };
+        let mut cells = Vec::new();
+
+        for (&(offset, token), bar) in tokens[first + 1..=last].iter().zip(&bars[first + 1..]) {
+            match bar {
+                Some(end) => row.measures.push(Measure {
+                    cells: std::mem::take(&mut cells),
+                    end: *end,
+                }),
+                None => cells.push(GridCell::parse(token, offset, ctx)),
+            }
+        }
+
+        row
+    }
+}
+
+impl Section {
+    /// Builds the section for a grid, checking that it is closed
+    fn from_grid<'a>(pair: Pair<'a, Rule>, ctx: &mut Context<'a>) -> Self {
+        let mut grid = Grid::default();
+        let mut start = None;
+        let mut terminated = false;
+
+        for child in pair.into_inner() {
+            match child.as_rule() {
+                Rule::start_grid => {
+                    start = Some(child.as_span());
+                    let args = child
+                        .into_inner()
+                        .find(|p| p.as_rule() == Rule::args)
+                        .map(|args| args.as_str())
+                        .unwrap_or("");
+
+                    grid.shape = attribute(args, "shape")
+                        .or(Some(args).filter(|a| !a.contains('=')))
+                        .and_then(parse_shape);
+                    if grid.shape.is_none() || args.contains('=') {
+                        grid.label = parse_label(args);
+                    }
+                }
+                Rule::grid_line => grid.rows.push(GridRow::from_line(child, ctx)),
+                Rule::grid_end => terminated = true,
+                _ => {}
+            }
+        }
+
+        if let (Some(start), false) = (start, terminated) {
+            ctx.error(ErrorKind::UnterminatedEnvironment, start);
+        }
+
+        Section::Grid(grid)
+    }
+}
+
+/// Reads the arguments of a `{define}` or `{chord}` directive
+fn parse_definition<'a>(args: &'a str, show: bool, ctx: &mut Context<'a>) -> Option<ChordDefinition> {
+    let mut tokens = args.split_whitespace().peekable();
+    let name = tokens.next()?;
+    let mut definition = ChordDefinition {
+        name: name.to_owned(),
+        // Chord diagrams may be named with letters in any song, so they do
+        // not set the note names
+        chord: parse_absolute(name, ctx)
+            .map(|(_, chord)| chord)
+            .or_else(|| parse_relative(name, ctx)),
+        show,
+        ..Default::default()
+    };
+
+    let is_keyword = |t: &&str| matches!(*t, "base-fret" | "frets" | "fingers" | "keys");
+    let unused = |t: &str| match t {
+        "x" | "X" | "N" | "-" | "-1" => Some(None),
+        n => n.parse().ok().map(Some),
+    };
+
+    while let Some(keyword) = tokens.next() {
+        let mut values = Vec::new();
+        while let Some(value) = tokens.next_if(|t| !is_keyword(t)) {
+            values.push(value);
+        }
+
+        match keyword {
+            "base-fret" => match values[..] {
+                [fret] => definition.base_fret = Some(fret.parse().ok()?),
+                _ => return None,
+            },
+            "frets" => definition.frets = values.into_iter().map(unused).collect::<Option<_>>()?,
+            "fingers" => definition.fingers = values.into_iter().map(unused).collect::<Option<_>>()?,
+            "keys" => definition.keys = values.into_iter().map(|k| k.parse().ok()).collect::<Option<_>>()?,
+            _ => return None,
+        }
+    }
+
+    Some(definition)
+}
+
 impl Song {
-    fn parse_directive<'a, 'b>(&'b mut self, mut pairs: Pairs<'a, Rule>) {
-        let name = pairs.next();
-        let data = pairs.next();
+    fn parse_directive<'a>(&mut self, pairs: Pairs<'a, Rule>, ctx: &mut Context<'a>) {
+        let mut name = None;
+        let mut condition = None;
+        let mut data = None;
+
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::directive_name => name = Some(pair),
+                Rule::selector => condition = Some(Condition::parse(pair.as_str())),
+                _ => data = Some(pair),
+            }
+        }
 
         if let Some(name) = name {
-            if name.as_rule() == Rule::directive_name {
-                match name.as_str() {
-                    "title" => {
-                        self.title = data.map(|x| x.as_str()).unwrap_or("").to_owned();
-                    },
-                    "artist" => {
-                        self.artist = data.map(|x| x.as_str()).unwrap_or("").to_owned();
-                    },
-                    "capo" => {
-                        let capo_str = data.map(|x| x.as_str()).unwrap_or("");
+            let value = data.as_ref().map(|x| x.as_str()).unwrap_or("");
+            let name_span = name.as_span();
 
-                        if let Ok(capo) = u8::from_str(capo_str) {
-                            self.capo = capo;
-                        } else {
-                            self.capo = 0;
+            match name.as_str() {
+                "meta" => {
+                    let (name, value) = value.split_once(char::is_whitespace).unwrap_or((value, ""));
+                    if !name.is_empty() {
+                        self.insert_metadata(condition, name, value.trim_start());
+                    }
+                }
+                "define" | "chord" => match parse_definition(value, name.as_str() == "chord", ctx) {
+                    Some(definition) => self.definitions.push(ChordDefinition {
+                        condition,
+                        ..definition
+                    }),
+                    None => ctx.error(ErrorKind::MalformedDefinition, data.as_ref().unwrap_or(&name).as_span()),
+                },
+                custom if custom.starts_with("x_") => {}
+                name => {
+                    if name == "key" && condition.is_none() {
+                        ctx.key = Key::parse_with(value, ctx.names).ok();
+                    }
+
+                    // Conditional values are only checked here, and applied by `Song::select`
+                    let standard = match condition {
+                        Some(_) => Metadata::default().insert_standard(name, value),
+                        None => self.metadata.insert_standard(name, value),
+                    };
+
+                    match standard {
+                        Inserted::Unknown => ctx.warning(ErrorKind::UnknownDirective, name_span),
+                        Inserted::Invalid => {
+                            ctx.warning(ErrorKind::MalformedMetadata, data.as_ref().map_or(name_span, |d| d.as_span()))
                         }
+                        Inserted::Standard => {}
+                    }
+                    if standard != Inserted::Unknown && condition.is_some() {
+                        self.insert_metadata(condition, name, value);
                     }
-                    _ => {}
                 }
             }
         }
     }
+
+    /// Inserts a metadata item, or keeps it apart if it has a condition
+    fn insert_metadata(&mut self, condition: Option<Condition>, name: &str, value: &str) {
+        match condition {
+            Some(condition) => self
+                .metadata
+                .conditional
+                .push((condition, name.to_owned(), value.to_owned())),
+            None => self.metadata.insert(name, value),
+        }
+    }
 }
 
 impl HasRule for Song {
@@ -219,13 +1041,19 @@ impl HasRule for Song {
 }
 
 impl<'a> ProcessChild<'a> for Song {
-    fn process_child(&mut self, pair: Pair<'a, Rule>) {
+    fn process_child(&mut self, pair: Pair<'a, Rule>, ctx: &mut Context<'a>) {
         match pair.as_rule() {
             Rule::directive => {
-                self.parse_directive(pair.into_inner());
+                self.parse_directive(pair.into_inner(), ctx);
             }
             Rule::section => {
-                self.song.push(Section::from_pair(pair));
+                self.song.push(Section::from_pair(pair, ctx));
+            }
+            Rule::stray_end => ctx.warning(ErrorKind::StrayEnd, pair.as_span()),
+            Rule::invalid => {
+                let span = pair.as_span();
+                let error = syntax_error(ctx.source, span.start(), span.end());
+                ctx.report(Severity::Error, error);
             }
             _ => {}
         }
@@ -235,6 +1063,7 @@ impl<'a> ProcessChild<'a> for Song {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metadata::Metadata;
 
     macro_rules! chord {
         ($s:expr) => {
@@ -251,15 +1080,24 @@ mod tests {
         }};
     }
     
+    macro_rules! error_test {
+        ($type:ty { $input:expr => $kind:expr, ($line:expr, $column:expr) }) => {{
+            let error = <$type>::from_str($input).expect_err("Parsed invalid input");
+            assert_eq!(error.kind, $kind);
+            assert_eq!((error.span.start.line, error.span.start.column), ($line, $column));
+        }};
+    }
+
     impl SongParser {
-        fn parse_str<'a, T>(s: &'a str) -> Result<T, pest::error::Error<Rule>>
+        fn parse_str<'a, T>(s: &'a str) -> Result<T, ParseError>
         where
             T: HasRule + FromPair<'a>,
         {
-            let content = SongParser::parse(T::MATCH_RULE, s)?;
+            let content = SongParser::parse(T::MATCH_RULE, s).map_err(|e| pest_error(s, e))?;
 
-            let pair = content.peek().unwrap();
-            Ok(T::from_pair(pair))
+            let mut ctx = Context::new(s);
+            let value = T::from_pair(content.peek().unwrap(), &mut ctx);
+            ctx.finish(value)
         }
     }
     #[test]
@@ -322,7 +1160,7 @@ mod tests {
                     Chunk::Chord(chord!("G")),
                     Chunk::Lyrics(" year after year".to_string())
                 ])
-            ]))
+            ]).into())
         })
     }
 
@@ -351,9 +1189,12 @@ mod tests {
             We're just [Am]two lost souls swimming in a fish bowl,[G] year after year
             {eoc}"#
             => Song{
-                title: "Wish You Were Here".to_string(),
-                artist: "Pink Floyd".to_string(),
-                capo: 0,
+                metadata: Metadata {
+                    title: "Wish You Were Here".to_string(),
+                    artists: vec!["Pink Floyd".to_string()],
+                    ..Default::default()
+                },
+                definitions: vec![],
                 song: vec![Section::Chorus(Paragraph(vec![
                     Line(vec![
                         Chunk::Chord(chord!("C")),
@@ -368,7 +1209,7 @@ mod tests {
                         Chunk::Chord(chord!("G")),
                         Chunk::Lyrics(" year after year".to_string())
                     ])
-                ]))]
+                ]).into())]
             }
         })
     }
@@ -381,9 +1222,8 @@ mod tests {
             [C]How I wish, how I wish you were [D]here
             We're just [Am]two lost souls swimming in a fish bowl,[G] year after year"#
             => Song{
-                title: "".to_string(),
-                artist: "".to_string(),
-                capo: 0,
+                metadata: Metadata::default(),
+                definitions: vec![],
                 song: vec![Section::Verse(Paragraph(vec![
                     Line(vec![
                         Chunk::Chord(chord!("C")),
@@ -403,17 +1243,568 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_environment_parse() {
+        let lyrics = |s: &str| Paragraph(vec![Line(vec![Chunk::Lyrics(s.to_string())])]);
+
+        parse_test!( Song {
+            r#"{sov: Verse 1}
+            First verse
+            {eov}
+            {soc: label="Chorus 2"}
+            The chorus
+            {end_of_chorus}
+            {start_of_bridge}
+            The bridge
+            {eob}
+            {start_of_outro}
+            The end
+            {end_of_outro}"#
+            => Song {
+                metadata: Metadata::default(),
+                definitions: vec![],
+                song: vec![
+                    Section::Environment(EnvironmentKind::Verse, Environment {
+                        label: Some("Verse 1".to_string()),
+                        content: lyrics("First verse"),
+                    }),
+                    Section::Chorus(Environment {
+                        label: Some("Chorus 2".to_string()),
+                        content: lyrics("The chorus"),
+                    }),
+                    Section::Environment(EnvironmentKind::Bridge, lyrics("The bridge").into()),
+                    Section::Environment(
+                        EnvironmentKind::Custom("outro".to_string()),
+                        lyrics("The end").into()
+                    ),
+                ]
+            }
+        });
+
+        error_test!(Song { "{sov}\nVerse\n{end_of_bridge}" => ErrorKind::UnterminatedEnvironment, (1, 1) });
+    }
+
+    #[test]
+    fn test_tab_parse() {
+        parse_test!( Section {
+            "{sot: Riff}\ne|-----[0]---|\n  B|--1--  3--|\n\n  {eot}"
+            => Section::Tab(Tab {
+                label: Some("Riff".to_string()),
+                lines: vec![
+                    "e|-----[0]---|".to_string(),
+                    "  B|--1--  3--|".to_string(),
+                    "".to_string(),
+                ],
+            })
+        });
+
+        error_test!(Song { "{start_of_tab}\ne|---|" => ErrorKind::UnterminatedEnvironment, (1, 1) });
+
+        // Trailing whitespace after the start directive
+        parse_test!( Section {
+            "{sot} \t\ne|-[0]-|\n{eot}"
+            => Section::Tab(Tab {
+                label: None,
+                lines: vec!["e|-[0]-|".to_string()],
+            })
+        });
+        let (_, diagnostics) = Song::parse_lenient("{sot} \ne|-[0]-|\n{eot}");
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_grid_parse() {
+        let cell = |s: &str| GridCell::Chord(chord!(s));
+
+        parse_test!( Section {
+            "{start_of_grid: shape=\"1+4x2+2\" label=\"Intro\"}\n\
+            A  |: C . | G~D7 % |1 Am . :| x2\n\
+            |2 F . |.\n\
+            \n\
+            {end_of_grid}"
+            => Section::Grid(Grid {
+                label: Some("Intro".to_string()),
+                shape: Some(GridShape { margin: 1, measures: 4, beats: 2, comment: 2 }),
+                rows: vec![
+                    GridRow {
+                        margin: Some("A".to_string()),
+                        start: Some(BarLine::RepeatStart),
+                        measures: vec![
+                            Measure { cells: vec![cell("C"), GridCell::Continue], end: BarLine::Single },
+                            Measure {
+                                cells: vec![GridCell::Split(vec![chord!("G"), chord!("D7")]), GridCell::RepeatMeasure],
+                                end: BarLine::Volta(1),
+                            },
+                            Measure { cells: vec![cell("Am"), GridCell::Continue], end: BarLine::RepeatEnd },
+                        ],
+                        comment: Some("x2".to_string()),
+                    },
+                    GridRow {
+                        margin: None,
+                        start: Some(BarLine::Volta(2)),
+                        measures: vec![
+                            Measure { cells: vec![cell("F"), GridCell::Continue], end: BarLine::End },
+                        ],
+                        comment: None,
+                    },
+                    GridRow::default(),
+                ],
+            })
+        });
+
+        parse_test!( Section {
+            "{start_of_grid 4x4}\n{end_of_grid}"
+            => Section::Grid(Grid {
+                label: None,
+                shape: Some(GridShape { margin: 0, measures: 4, beats: 4, comment: 0 }),
+                rows: vec![],
+            })
+        });
+
+        error_test!(Song { "{sog}\n| C . H . |\n{eog}" => ErrorKind::MalformedChord, (2, 7) });
+
+        // Trailing whitespace after the start directive
+        parse_test!( Section {
+            "{sog} \t\n| C . |\n{eog}"
+            => Section::Grid(Grid {
+                label: None,
+                shape: None,
+                rows: vec![GridRow {
+                    margin: None,
+                    start: Some(BarLine::Single),
+                    measures: vec![Measure { cells: vec![cell("C"), GridCell::Continue], end: BarLine::Single }],
+                    comment: None,
+                }],
+            })
+        });
+        let (_, diagnostics) = Song::parse_lenient("{sog} \n| C . |\n{eog}");
+        assert!(diagnostics.is_empty());
+
+        // Repeats with double bars and voltas
+        parse_test!( Section {
+            "{sog}\n||: C . | G . :|2 F . :||\n{eog}"
+            => Section::Grid(Grid {
+                label: None,
+                shape: None,
+                rows: vec![GridRow {
+                    margin: None,
+                    start: Some(BarLine::DoubleRepeatStart),
+                    measures: vec![
+                        Measure { cells: vec![cell("C"), GridCell::Continue], end: BarLine::Single },
+                        Measure { cells: vec![cell("G"), GridCell::Continue], end: BarLine::RepeatEndVolta(2) },
+                        Measure { cells: vec![cell("F"), GridCell::Continue], end: BarLine::DoubleRepeatEnd },
+                    ],
+                    comment: None,
+                }],
+            })
+        });
+        let (_, diagnostics) = Song::parse_lenient("{sog}\n|| C . | G . :|2 F . ||\n{eog}");
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_chorus_ref_parse() {
+        parse_test!(Section { "{chorus}" => Section::ChorusRef(None) });
+        parse_test!(Section { "{chorus: Last chorus}" => Section::ChorusRef(Some("Last chorus".to_string())) });
+    }
+
+    #[test]
+    fn test_transpose_parse() {
+        parse_test!(Section { "{transpose: 2}" => Section::Transpose(2) });
+        parse_test!(Section { "{transpose: -3}" => Section::Transpose(-3) });
+        parse_test!(Section { "{transpose: +1}" => Section::Transpose(1) });
+        parse_test!(Section { "{transpose}" => Section::Transpose(0) });
+
+        error_test!(Song { "{transpose: up}" => ErrorKind::MalformedTranspose, (1, 13) });
+    }
+
+    #[test]
+    fn test_definition_parse() {
+        let song: Song = SongParser::parse_str(
+            r#"{define: Am base-fret 1 frets x 0 2 2 1 0 fingers - 0 2 3 1 0}
+            {chord: C keys 0 4 7}
+            {define: Am base-fret 5 frets 0 2 2 0 0 0}"#,
+        )
+        .expect("Failed to parse");
+
+        assert_eq!(
+            song.definitions[0],
+            ChordDefinition {
+                name: "Am".to_string(),
+                chord: Some(chord!("Am")),
+                base_fret: Some(1),
+                frets: vec![None, Some(0), Some(2), Some(2), Some(1), Some(0)],
+                fingers: vec![None, Some(0), Some(2), Some(3), Some(1), Some(0)],
+                keys: vec![],
+                show: false,
+                condition: None,
+            }
+        );
+        assert_eq!(song.definitions[1].keys, vec![0, 4, 7]);
+        assert!(song.definitions[1].show);
+        assert_eq!(song.chord_definition(&chord!("Am")), Some(&song.definitions[2]));
+        assert_eq!(song.chord_definition(&chord!("G")), None);
+
+        error_test!(Song { "{define: Am base-fret one}" => ErrorKind::MalformedDefinition, (1, 10) });
+    }
+
+    #[test]
+    fn test_conditional_parse() {
+        let song: Song = SongParser::parse_str(
+            "{title-guitar: For guitar}\n\
+             {meta-!bass: label Records}\n\
+             {start_of_chorus-ukulele}\n\
+             [C]Chorus\n\
+             {end_of_chorus}\n\
+             {c-keys!: Comment}\n\
+             {chorus-ukulele}\n\
+             {sog-bass}\n\
+             | C . | G . |\n\
+             {eog}\n",
+        )
+        .expect("Failed to parse");
+
+        let condition = |name: &str, negated| Condition {
+            name: name.to_string(),
+            negated,
+        };
+
+        assert_eq!(song.metadata.title, "");
+        assert_eq!(
+            song.metadata.conditional,
+            vec![
+                (condition("guitar", false), "title".to_string(), "For guitar".to_string()),
+                (condition("bass", true), "label".to_string(), "Records".to_string()),
+            ]
+        );
+        assert!(matches!(
+            &song.song[..],
+            [
+                Section::Conditional(c1, chorus),
+                Section::Conditional(c2, comment),
+                Section::Conditional(c3, chorus_ref),
+                Section::Conditional(c4, grid),
+            ] if *c1 == condition("ukulele", false)
+                && matches!(**chorus, Section::Chorus(_))
+                && *c2 == condition("keys", true)
+                && matches!(**comment, Section::Comment(_))
+                && *c3 == condition("ukulele", false)
+                && matches!(**chorus_ref, Section::ChorusRef(None))
+                && *c4 == condition("bass", false)
+                && matches!(**grid, Section::Grid(_))
+        ));
+
+        // Only the start directive gives the condition
+        let song: Song = SongParser::parse_str("{soc}\nChorus\n{eoc-guitar}\n\n{sot}\ne|---|\n{eot-bass}")
+            .expect("Failed to parse");
+        assert!(matches!(&song.song[..], [Section::Chorus(_), Section::Tab(_)]));
+    }
+
+    #[test]
+    fn test_metadata_parse() {
+        let song: Song = SongParser::parse_str(
+            r#"{t: Bohemian Rhapsody}
+            {st: A Night at the Opera}
+            {artist: Queen}
+            {composer: Freddie Mercury}
+            {composer: Someone Else}
+            {key: Bb}
+            {tempo: 72}
+            {meta: label EMI}
+            {meta: year 1975}"#,
+        )
+        .expect("Failed to parse");
+
+        assert_eq!(song.metadata.title, "Bohemian Rhapsody");
+        assert_eq!(song.metadata.subtitles, vec!["A Night at the Opera"]);
+        assert_eq!(song.metadata.artists, vec!["Queen"]);
+        assert_eq!(song.metadata.composers, vec!["Freddie Mercury", "Someone Else"]);
+        assert_eq!(song.metadata.key.as_deref(), Some("Bb"));
+        assert_eq!(song.metadata.tempo, Some(72));
+        assert_eq!(song.metadata.year, Some(1975));
+        assert_eq!(song.metadata.meta["label"], vec!["EMI"]);
+    }
+
+    #[test]
+    fn test_malformed_metadata() {
+        let (song, diagnostics) = Song::parse_lenient("{year: unknown}\n{tempo: fast}\n{capo: 2}\n{capo}");
+
+        assert_eq!(song.metadata.year, None);
+        assert_eq!(song.metadata.tempo, None);
+        assert_eq!(song.metadata.capo, 2);
+        assert_eq!(song.metadata.meta["year"], vec!["unknown"]);
+        assert_eq!(song.metadata.meta["tempo"], vec!["fast"]);
+        assert_eq!(song.metadata.meta["capo"], vec![""]);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.error.kind, d.error.span.start.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Severity::Warning, ErrorKind::MalformedMetadata, 1),
+                (Severity::Warning, ErrorKind::MalformedMetadata, 2),
+                (Severity::Warning, ErrorKind::MalformedMetadata, 4),
+            ]
+        );
+        assert!(Song::from_str("{year: unknown}").is_ok());
+    }
+
     #[test]
     fn test_chord() {
         parse_test!( Chord {
             "C#dim4/G"
             => Chord{
-                root: Note::CSharp,
-                bass: Note::G,
-                minor: false,
-                number: 4,
-                others: "dim".to_string()
+                root: Note::CSharp.into(),
+                quality: ChordQuality::Diminished,
+                extensions: vec![Extension::Degree(None, 4)],
+                bass: Note::G.into(),
             }
-        })
+        });
+        parse_test!( Chord {
+            "G7b9#11"
+            => Chord{
+                root: Note::G.into(),
+                quality: ChordQuality::Major,
+                extensions: vec![
+                    Extension::Degree(None, 7),
+                    Extension::Degree(Some(Alteration::Flat), 9),
+                    Extension::Degree(Some(Alteration::Sharp), 11),
+                ],
+                bass: Note::G.into(),
+            }
+        });
+        parse_test!( Chord {
+            "Cm9(b5,omit3)add13"
+            => Chord{
+                root: Note::C.into(),
+                quality: ChordQuality::HalfDiminished,
+                extensions: vec![
+                    Extension::Degree(None, 9),
+                    Extension::Omit(3),
+                    Extension::Add(None, 13),
+                ],
+                bass: Note::C.into(),
+            }
+        });
+        parse_test!( Chord {
+            "Cm(maj7)"
+            => Chord{
+                root: Note::C.into(),
+                quality: ChordQuality::Minor,
+                extensions: vec![Extension::Major(7)],
+                bass: Note::C.into(),
+            }
+        });
+        parse_test!( Chord {
+            "C6/9/E"
+            => Chord{
+                root: Note::C.into(),
+                quality: ChordQuality::Major,
+                extensions: vec![Extension::Degree(None, 6), Extension::Add(None, 9)],
+                bass: Note::E.into(),
+            }
+        });
+        assert_eq!(chord!("C6/9"), chord!("C6add9"));
+        assert_eq!(chord!("Am6/9").to_string(), "Am6/9");
+        assert_eq!(chord!("C6/9/E").to_string(), "C6/9/E");
+        assert_eq!(chord!("C7sus2").quality, ChordQuality::Suspended2);
+        assert_eq!(chord!("Cmaj").quality, ChordQuality::Major);
+        assert_eq!(chord!("C13"), chord!("C(13)"));
+        assert_eq!(chord!("Csus"), chord!("Csus4"));
+        assert_eq!(chord!("Cno5"), chord!("Comit5"));
+    }
+
+    #[test]
+    fn test_latin_chord() {
+        let chord = |s| Chord::from_str(s).unwrap().to_string();
+
+        assert_eq!(chord("Do"), "C");
+        assert_eq!(chord("Ut"), "C");
+        assert_eq!(chord("Lam"), "Am");
+        assert_eq!(chord("Sol7"), "G7");
+        assert_eq!(chord("Sib"), "Bb");
+        assert_eq!(chord("Re#m7"), "D#m7");
+        assert_eq!(chord("FA/DO"), "F/C");
+        assert_eq!(chord("Mimaj7"), "Emaj7");
+        assert_eq!(Key::from_str("Lam").unwrap(), Key::from_str("Am").unwrap());
+
+        let song = "{key: Sol}\n[Sol]Uno [Mim]dos [Do]tres [Re7/Fa#]cuatro";
+        let chords = |song: Song| song.chords().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(chords(Song::from_str(song).unwrap()), vec!["G", "Em", "C", "D7/F#"]);
+        assert_eq!(chords(Song::parse_with(song, NoteNames::Latin).unwrap()), vec!["G", "Em", "C", "D7/F#"]);
+
+        assert!(Song::parse_with(song, NoteNames::Letters).is_err());
+        assert!(Song::parse_with("[Am]Letters", NoteNames::Latin).is_err());
+        assert!(Chord::parse_with("La", NoteNames::Letters).is_err());
+        assert!(Chord::from_str("Lo").is_err());
+    }
+
+    #[test]
+    fn test_mixed_note_names() {
+        let (song, diagnostics) = Song::parse_lenient("{define: Am base-fret 1 frets x 0 2 2 1 0}\n[Do]Uno [Lam]dos [C]tres");
+        let chords: Vec<String> = song.chords().map(|c| c.to_string()).collect();
+        assert_eq!(chords, vec!["C", "Am", "C"]);
+
+        // Only the chord after the first one with other names is reported
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].error.kind, ErrorKind::MixedNoteNames);
+        let start = &diagnostics[0].error.span.start;
+        assert_eq!((start.line, start.column), (2, 19));
+
+        let (_, diagnostics) = Song::parse_lenient("[C]One\n{sog}\n| Sol . C~Re . |\n{eog}");
+        let kinds: Vec<ErrorKind> = diagnostics.iter().map(|d| d.error.kind).collect();
+        assert_eq!(kinds, vec![ErrorKind::MixedNoteNames, ErrorKind::MixedNoteNames]);
+
+        let (_, diagnostics) = Song::parse_lenient("[Do]Uno [Lam]dos");
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_german_chord() {
+        let chord = |s| Chord::parse_with(s, NoteNames::German).unwrap().to_string();
+
+        assert_eq!(chord("H7"), "B7");
+        assert_eq!(chord("B"), "Bb");
+        assert_eq!(chord("Bm/F"), "Bbm/F");
+        assert_eq!(chord("G/H"), "G/B");
+        assert_eq!(chord("Hb"), "Bb");
+        assert_eq!(chord("F#m"), "F#m");
+        assert_eq!(Key::parse_with("H", NoteNames::German).unwrap(), Key::from_str("B").unwrap());
+
+        let song = Song::parse_with("{key: B}\n[B]Eins [H7]zwei [1]drei", NoteNames::German).unwrap();
+        let chords: Vec<String> = song.chords().map(|c| c.to_string()).collect();
+        assert_eq!(chords, vec!["Bb", "B7", "Bb"]);
+
+        // Letter names are still the default
+        assert_eq!(Chord::from_str("B").unwrap().to_string(), "B");
+        assert!(Chord::parse_with("Do", NoteNames::German).is_err());
+    }
+
+    #[test]
+    fn test_typographic_chord() {
+        let chord = |s| Chord::from_str(s).unwrap().to_string();
+
+        assert_eq!(chord("C♯m7♭5"), "C#m7b5");
+        assert_eq!(chord("B♭Δ7"), "Bbmaj7");
+        assert_eq!(chord("E−7"), "Em7");
+        assert_eq!(chord("F°7"), "Fdim7");
+        assert_eq!(chord("Gø"), "Gm7b5");
+        assert_eq!(chord("A𝄫/F𝄪"), "Abb/F##");
+        assert_eq!(chord("Sol♯m"), "G#m");
+        assert_eq!(Key::from_str("E♭−").unwrap(), Key::from_str("Ebm").unwrap());
+    }
+
+    #[test]
+    fn test_relative_chord() {
+        let key = |s| Key::from_str(s).unwrap();
+        let relative = |s, k| Chord::from_relative(s, key(k)).unwrap().to_string();
+
+        assert_eq!(relative("1", "C"), "C");
+        assert_eq!(relative("6m", "C"), "Am");
+        assert_eq!(relative("5/7", "C"), "G/B");
+        assert_eq!(relative("b7", "F"), "Eb");
+        assert_eq!(relative("2m7", "Bb"), "Cm7");
+        assert_eq!(relative("vi", "C"), "Am");
+        assert_eq!(relative("vi7", "C"), "Am7");
+        assert_eq!(relative("V/vii", "C"), "G/B");
+        assert_eq!(relative("vii°", "D"), "C#dim");
+        assert_eq!(relative("bVII", "D"), "C");
+        assert_eq!(relative("III", "Em"), "G");
+        assert_eq!(relative("IV/C", "G"), "C");
+        assert_eq!(relative("4/E", "G"), "C/E");
+
+        let song = Song::from_str("{key: D}\n[1]One [IV]two [6m]three").unwrap();
+        let chords: Vec<String> = song.chords().map(|c| c.to_string()).collect();
+        assert_eq!(chords, vec!["D", "G", "Bm"]);
+
+        error_test!(Song { "[1]No key" => ErrorKind::MalformedChord, (1, 2) });
+        assert!(Chord::from_relative("8", key("C")).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        error_test!(Song { "This is a [H7]chord" => ErrorKind::MalformedChord, (1, 12) });
+        error_test!(Song { "Some lyrics\nwith a stray]" => ErrorKind::StrayBracket, (2, 13) });
+        error_test!(Song { "Some [Am lyrics" => ErrorKind::UnterminatedChord, (1, 6) });
+        error_test!(Song { "{title: Song}\n{soc}\n[C]Chorus" => ErrorKind::UnterminatedChorus, (2, 1) });
+        error_test!(Song { "{title: Song\n[C]Lyrics" => ErrorKind::UnterminatedDirective, (1, 1) });
+        error_test!(Chord { "H7" => ErrorKind::MalformedChord, (1, 1) });
+        error_test!(Chord { "Cxyz" => ErrorKind::MalformedChord, (1, 1) });
+        error_test!(Note { "" => ErrorKind::MalformedNote, (1, 1) });
+    }
+
+    #[test]
+    fn test_error_message() {
+        let error = Song::from_str("{title: Song}\n\nThis is a [H7]chord").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "malformed chord at line 3, column 12\n  |\n3 | This is a [H7]chord\n  |            ^^"
+        );
+    }
+
+    #[test]
+    fn test_lenient_parse() {
+        let (song, diagnostics) = Song::parse_lenient(
+            r#"{title: Song}
+            {unknown: directive}
+            This is a [H7]chord
+            {eoc}
+            {soc}
+            [C]Chorus"#,
+        );
+
+        assert_eq!(
+            song.song,
+            vec![
+                Section::Verse(Paragraph(vec![Line(vec![
+                    Chunk::Lyrics("This is a ".to_string()),
+                    Chunk::Lyrics("[H7]".to_string()),
+                    Chunk::Lyrics("chord".to_string()),
+                ])])),
+                Section::Chorus(Paragraph(vec![Line(vec![
+                    Chunk::Chord(chord!("C")),
+                    Chunk::Lyrics("Chorus".to_string()),
+                ])]).into()),
+            ]
+        );
+
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.error.kind, d.error.span.start.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Severity::Warning, ErrorKind::UnknownDirective, 2),
+                (Severity::Error, ErrorKind::MalformedChord, 3),
+                (Severity::Warning, ErrorKind::StrayEnd, 4),
+                (Severity::Error, ErrorKind::UnterminatedChorus, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stray_end() {
+        let song = Song::from_str("Hello\n{eoc}\nWorld\n").expect("Failed to parse song");
+        let lyrics: Vec<String> = song
+            .iter()
+            .flat_map(|s| s.iter())
+            .flat_map(|l| l.iter())
+            .map(|c| match c {
+                Chunk::Lyrics(s) => s.clone(),
+                Chunk::Chord(c) => c.to_string(),
+            })
+            .collect();
+        assert_eq!(lyrics, vec!["Hello", "World"]);
+
+        let (_, diagnostics) = Song::parse_lenient("Hello\n{end_of_bridge}\nWorld\n");
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.error.kind, d.error.span.start.line))
+            .collect();
+        assert_eq!(found, vec![(Severity::Warning, ErrorKind::StrayEnd, 2)]);
     }
 }
@@ -0,0 +1,262 @@
+//! # Text module
+//! This module renders a `Song` as plain text, with the chords over the
+//! lyrics, for monospaced fonts.
+//!
+//! Wrap a reference to the song in `PlainText`, which implements the
+//! `std::fmt::Display` trait, or call `Song::to_text()`:
+//! ```
+//! # use chordpro::Song;
+//! # use std::str::FromStr;
+//! let song = Song::from_str("[G]Some [D/F#]lyrics").unwrap();
+//!
+//! assert_eq!(song.to_text(), "G    D/F#\nSome lyrics\n");
+//! ```
+//!
+//! Each chord is written over the lyric column where it is played. When a
+//! chord does not fit before the next one, the lyrics are spread out with
+//! spaces, or with dashes inside a word:
+//! ```text
+//! Dm7 G7sus4 C
+//! Hal-le-----lujah
+//! ```
+//! `Line`s are rendered the same way with `TextLine`.
+//!
+//! The song is rendered as it is: apply `Song::select` and
+//! `Song::transposed` first to show it for an instrument and with its
+//! `{transpose}` directives.
+
+use crate::format::{Notated, Notation};
+use crate::grid::NotatedRow;
+use crate::song::{Chunk, EnvironmentKind, Line, Paragraph, Section, Song};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Wrapper to format a song as plain text, with the chords over the lyrics
+pub struct PlainText<'a>(pub &'a Song, pub Notation);
+
+impl<'a> Display for PlainText<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let PlainText(song, notation) = *self;
+        let metadata = &song.metadata;
+        let mut separate = false;
+
+        for header in std::iter::once(&metadata.title)
+            .filter(|title| !title.is_empty())
+            .chain(metadata.subtitles.iter())
+            .chain(metadata.artists.iter())
+        {
+            writeln!(f, "{}", header)?;
+            separate = true;
+        }
+
+        for section in song.iter() {
+            // Directives that are not shown leave no blank line
+            if matches!(section, Section::Transpose(_)) {
+                continue;
+            }
+            if separate {
+                writeln!(f)?;
+            }
+            write_section(f, section, notation)?;
+            separate = true;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> AsRef<Song> for PlainText<'a> {
+    fn as_ref(&self) -> &Song {
+        self.0
+    }
+}
+
+fn write_section(f: &mut Formatter, section: &Section, notation: Notation) -> fmt::Result {
+    match section {
+        Section::Chorus(e) => {
+            writeln!(f, "{}:", e.label.as_deref().unwrap_or("Chorus"))?;
+            write_paragraph(f, &e.content, notation)
+        }
+        Section::Verse(p) => write_paragraph(f, p, notation),
+        Section::Comment(l) => writeln!(f, "{}", TextLine(l, notation)),
+        Section::Environment(kind, e) => {
+            let heading = match (&e.label, kind) {
+                (Some(label), _) => Some(label.as_str()),
+                (None, EnvironmentKind::Bridge) => Some("Bridge"),
+                (None, _) => None,
+            };
+            if let Some(heading) = heading {
+                writeln!(f, "{}:", heading)?;
+            }
+            write_paragraph(f, &e.content, notation)
+        }
+        Section::Tab(tab) => {
+            for line in tab.label.iter().chain(tab.lines.iter()) {
+                writeln!(f, "{}", line)?;
+            }
+            Ok(())
+        }
+        Section::Grid(grid) => {
+            if let Some(label) = &grid.label {
+                writeln!(f, "{}", label)?;
+            }
+            for row in grid.rows.iter() {
+                writeln!(f, "{}", NotatedRow(row, notation))?;
+            }
+            Ok(())
+        }
+        Section::ChorusRef(label) => writeln!(f, "{}:", label.as_deref().unwrap_or("Chorus")),
+        Section::Transpose(_) => Ok(()),
+        Section::Conditional(_, section) => write_section(f, section, notation),
+    }
+}
+
+fn write_paragraph(f: &mut Formatter, paragraph: &Paragraph, notation: Notation) -> fmt::Result {
+    for line in paragraph.0.iter() {
+        writeln!(f, "{}", TextLine(line, notation))?;
+    }
+
+    Ok(())
+}
+
+/// Wrapper to format a line with its chords over the lyrics, in a
+/// `Notation`.
+///
+/// Lines with chords take two lines of text (without the last line break),
+/// and lines with only lyrics or only chords take one.
+pub struct TextLine<'a>(pub &'a Line, pub Notation);
+
+impl<'a> Display for TextLine<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let TextLine(line, notation) = *self;
+        let (chords, lyrics) = chords_over_lyrics(line, notation);
+
+        match (chords.is_empty(), lyrics.is_empty()) {
+            (true, _) => write!(f, "{}", lyrics),
+            (false, true) => write!(f, "{}", chords),
+            (false, false) => write!(f, "{}\n{}", chords, lyrics),
+        }
+    }
+}
+
+impl<'a> AsRef<Line> for TextLine<'a> {
+    fn as_ref(&self) -> &Line {
+        self.0
+    }
+}
+
+/// Lays out the chords of a line over its lyrics, as the chord line and the
+/// lyric line. Columns are counted in characters.
+fn chords_over_lyrics(line: &Line, notation: Notation) -> (String, String) {
+    let mut chords = String::new();
+    let mut lyrics = String::new();
+    let mut chords_width = 0;
+    let mut lyrics_width = 0;
+
+    for (i, chunk) in line.0.iter().enumerate() {
+        match chunk {
+            Chunk::Lyrics(s) => {
+                lyrics.push_str(s);
+                lyrics_width += s.chars().count();
+            }
+            Chunk::Chord(c) => {
+                // One space after the previous chord
+                let column = match chords_width {
+                    0 => 0,
+                    width => width + 1,
+                };
+
+                if column > lyrics_width {
+                    let next = line.0[i + 1..].iter().find_map(|chunk| match chunk {
+                        Chunk::Lyrics(s) => s.chars().next(),
+                        Chunk::Chord(_) => None,
+                    });
+                    let in_word = lyrics.chars().last().is_some_and(|c| !c.is_whitespace())
+                        && next.is_some_and(|c| !c.is_whitespace());
+                    let fill = if in_word { '-' } else { ' ' };
+
+                    lyrics.extend(std::iter::repeat(fill).take(column - lyrics_width));
+                    lyrics_width = column;
+                }
+
+                let chord = Notated(c, notation).to_string();
+                chords.extend(std::iter::repeat(' ').take(lyrics_width - chords_width));
+                chords.push_str(&chord);
+                chords_width = lyrics_width + chord.chars().count();
+            }
+        }
+    }
+
+    let lyrics_len = lyrics.trim_end().len();
+    lyrics.truncate(lyrics_len);
+    (chords, lyrics)
+}
+
+impl Song {
+    /// Renders the song as plain text, with the chords over the lyrics
+    pub fn to_text(&self) -> String {
+        PlainText(self, Notation::Standard).to_string()
+    }
+
+    /// Renders the song as plain text, with its chords in `notation`
+    pub fn to_text_in(&self, notation: Notation) -> String {
+        PlainText(self, notation).to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn render(line: &str, notation: Notation) -> String {
+        let song = Song::from_str(line).expect("Failed to parse song");
+        let line = song.iter().flat_map(|section| section.iter()).next().unwrap();
+        TextLine(line, notation).to_string()
+    }
+
+    #[test]
+    fn chords_over_lyrics() {
+        let text = |line| render(line, Notation::Standard);
+
+        assert_eq!(text("[G]Some [D/F#]lyrics"), "G    D/F#\nSome lyrics");
+        assert_eq!(text("Starts [C]later"), "       C\nStarts later");
+        assert_eq!(text("No chords"), "No chords");
+        assert_eq!(text("[C] [G] [Am]"), "C G Am");
+        assert_eq!(text("Ends with a [C]"), "            C\nEnds with a");
+    }
+
+    #[test]
+    fn chord_collisions() {
+        let text = |line| render(line, Notation::Standard);
+
+        // Between words the lyrics are spread with spaces
+        assert_eq!(text("[Cmaj7]A [G]day"), "Cmaj7 G\nA     day");
+        // and inside words with dashes
+        assert_eq!(text("[Dm7]Hal[G7sus4]le[C]lujah"), "Dm7 G7sus4 C\nHal-le-----lujah");
+        assert_eq!(text("[C]a[Gmaj7]b[D]c"), "C Gmaj7 D\na-b-----c");
+        // Chords together
+        assert_eq!(text("[C][G]Word"), "C G\n  Word");
+        assert_eq!(text("Wo[C][G]rd"), "  C G\nWo--rd");
+    }
+
+    #[test]
+    fn notation() {
+        assert_eq!(render("[Am]Hola [D7]amigo", Notation::Latin), "Lam  Re7\nHola amigo");
+        assert_eq!(render("[B]Ein [Bb]Lied", Notation::German), "H   B\nEin Lied");
+    }
+
+    #[test]
+    fn to_text() {
+        let song = Song::from_str(
+            "{title: Title}\n{artist: Artist}\n\n[C]Verse [G]line\n\n{soc}\n[F]Chorus\n{eoc}\n\n{transpose: 2}\n{chorus}\n{c: Slowly}",
+        )
+        .expect("Failed to parse song");
+
+        assert_eq!(
+            song.to_text(),
+            "Title\nArtist\n\nC     G\nVerse line\n\nChorus:\nF\nChorus\n\nChorus:\n\nSlowly\n"
+        );
+        assert_eq!(song.to_text_in(Notation::Latin).lines().nth(3), Some("Do    Sol"));
+    }
+}
@@ -1,24 +1,50 @@
 //! This module allows transposing chords in a `Song`
 //!
-use crate::song::{Chunk, Song};
+use crate::song::{Chunk, Section, Song};
 
-use crate::chords::Chord;
+use crate::chords::{interval, Chord, SpelledNote};
+use crate::key::Key;
 
 pub struct Transposer {
     s: i8,
+    key: Option<Key>,
 }
 
-pub fn map_to_chords<'a, F>(song: &mut Song, f: F)
+/// Which chords are shown for a song played with a `{capo}`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Pitch {
+    /// The chords as they are played with the capo, as written
+    #[default]
+    Played,
+    /// The chords as they sound, raised by the capo
+    Concert,
+}
+
+pub fn map_to_chords<F>(song: &mut Song, f: F)
 where
     F: Fn(&mut Chord),
 {
     for section in song.iter_mut() {
-        for line in section.iter_mut() {
-            for chunk in line.iter_mut() {
-                match chunk {
-                    Chunk::Chord(c) => f(c),
-                    _ => {}
-                }
+        map_section_chords(section, &f);
+    }
+}
+
+fn map_section_chords<F>(section: &mut Section, f: F)
+where
+    F: Fn(&mut Chord),
+{
+    let mut inner = &mut *section;
+    while let Section::Conditional(_, s) = inner {
+        inner = s;
+    }
+    if let Section::Grid(grid) = inner {
+        grid.chords_mut().for_each(&f);
+    }
+
+    for line in section.iter_mut() {
+        for chunk in line.iter_mut() {
+            if let Chunk::Chord(c) = chunk {
+                f(c);
             }
         }
     }
@@ -28,17 +54,59 @@ impl Transposer {
     /// Create a `Transposer` object which transposes song the specified
     /// amount of `semitones` (positive or negative)
     pub fn new(semitones: i8) -> Self {
-        Self { s: semitones }
+        Self {
+            s: semitones,
+            key: None,
+        }
+    }
+
+    /// Spells the transposed chords as they are written in `key`, the key
+    /// of the song after transposing it.
+    ///
+    /// Without it, the key is taken from the song's `{key}`, transposed.
+    /// If the song has no key either, the transposed notes keep using flats
+    /// or sharps as they did.
+    pub fn in_key(mut self, key: Key) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Create a `Transposer` that moves a song from the key `from` to
+    /// the key `to`, by the shortest interval, spelling the chords for `to`
+    pub fn to_key(from: Key, to: Key) -> Self {
+        let semitones = interval(from.tonic.pitch_class(), to.tonic.pitch_class()) as i8;
+        let semitones = if semitones > 6 { semitones - 12 } else { semitones };
+
+        Transposer::new(semitones).in_key(to)
     }
 
-    /// Applies transposition in-place
+    /// Applies transposition in-place. A `{key}` in the song is changed to
+    /// the transposed key.
     pub fn apply_transpose(&self, song: &mut Song) {
-        if self.s.abs() % 12 != 0 {
-            map_to_chords(song, |chord| {
-                chord.root = chord.root + self.s;
-                chord.bass = chord.bass + self.s;
-            });
+        // Nothing changes, not even the spelling of the key
+        if self.s % 12 == 0 && self.key.is_none() {
+            return;
         }
+
+        let declared = song.key();
+        let key = self.key.or_else(|| declared.map(|key| key.transpose(self.s)));
+
+        if let (Some(_), Some(key)) = (declared, key) {
+            song.set_key(key);
+        }
+
+        map_to_chords(song, |chord| self.transpose_chord(chord, key));
+    }
+
+    /// Transposes a chord, spelling it in `key` if there is one
+    fn transpose_chord(&self, chord: &mut Chord, key: Option<Key>) {
+        let transpose = |note: SpelledNote| match key {
+            Some(key) => key.spell(note.pitch_class() + self.s),
+            None => note + self.s,
+        };
+
+        chord.root = transpose(chord.root);
+        chord.bass = transpose(chord.bass);
     }
 
     /// Applies transposition to song
@@ -49,8 +117,83 @@ impl Transposer {
     }
 }
 
+impl Song {
+    /// Transposes the song to `key`, from its `{key}`. Songs without one
+    /// are taken to be in the key found by `Song::detect_key`.
+    ///
+    /// The mode of the song is kept: asking for `D` for a song in `Em`
+    /// gives `Dm`. The key metadata is set to the new key.
+    pub fn transpose_to(&mut self, key: Key) {
+        let from = match self.key() {
+            Some(from) => Some(from),
+            None => self.detect_key().first().map(|candidate| candidate.key),
+        };
+
+        if let Some(from) = from {
+            let to = Key::new(key.tonic, from.mode);
+            Transposer::to_key(from, to).apply_transpose(self);
+            self.set_key(to);
+        }
+    }
+
+    /// The song with its `{transpose}` directives applied to the chords
+    /// that follow them, and removed.
+    ///
+    /// With `Pitch::Concert`, the chords are also raised by the `{capo}`,
+    /// which is then removed. The `{key}` is changed as the start of the
+    /// song is. Conditional `{transpose}` directives are only applied after
+    /// `Song::select`.
+    /// ```
+    /// # use chordpro::{Pitch, Song};
+    /// # use std::str::FromStr;
+    /// let song = Song::from_str("{capo: 2}\n[G]Some lyrics").unwrap();
+    /// let concert = song.transposed(Pitch::Concert);
+    ///
+    /// assert_eq!(concert.chords().next().unwrap().to_string(), "A");
+    /// ```
+    pub fn transposed(&self, pitch: Pitch) -> Song {
+        let mut song = self.clone();
+        let capo = match pitch {
+            Pitch::Played => 0,
+            Pitch::Concert => i16::from(std::mem::take(&mut song.metadata.capo)),
+        };
+        // Any number of octaves comes from the song file
+        let offset = |semitones: i8| (i16::from(semitones) + capo).rem_euclid(12) as i8;
+        let declared = song.key();
+
+        let mut semitones = 0;
+        let mut start = None;
+        for section in song.song.iter_mut() {
+            match section {
+                Section::Transpose(s) => semitones = *s,
+                section => {
+                    let transposer = Transposer::new(offset(semitones));
+                    let key = declared.map(|key| key.transpose(transposer.s));
+                    start.get_or_insert(key);
+
+                    if transposer.s != 0 {
+                        map_section_chords(section, |chord| transposer.transpose_chord(chord, key));
+                    }
+                }
+            }
+        }
+
+        song.song.retain(|s| !matches!(s, Section::Transpose(_)));
+        if let Some(key) = start.unwrap_or(declared.map(|key| key.transpose(offset(semitones)))) {
+            song.set_key(key);
+        }
+        song
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use crate::song::Song;
+    use std::str::FromStr;
+
+    fn chords(song: &Song) -> Vec<String> {
+        song.chords().map(|c| c.to_string()).collect()
+    }
 
     #[test]
     fn transpose() {
@@ -61,7 +204,7 @@ mod test {
         let song = Song::from_str("[C]A [D#m]Cm").expect("Failed to parse song");
         let song2 = Transposer::new(-3).transpose(song);
 
-        let line = song2.iter().nth(0).unwrap().iter().nth(0).unwrap();
+        let line = song2.iter().next().unwrap().iter().next().unwrap();
         assert_eq!(
             line,
             &Line(vec![
@@ -72,4 +215,138 @@ mod test {
             ])
         );
     }
+
+    #[test]
+    fn transpose_in_key() {
+        use super::*;
+
+        let song = Song::from_str("[C]A [G7]B [A#]C").expect("Failed to parse song");
+        let to_f = Transposer::new(5).in_key(Key::from_str("F").unwrap()).transpose(song);
+        assert_eq!(chords(&to_f), vec!["F", "C7", "Eb"]);
+
+        let song = Song::from_str("{key: Dm}\n[Dm]A [A7/C#]B [Bb]C").expect("Failed to parse song");
+        let to_em = Transposer::new(2).transpose(song);
+        assert_eq!(chords(&to_em), vec!["Em", "B7/D#", "C"]);
+
+        let song = Song::from_str("[Db]A [Bbm]B").expect("Failed to parse song");
+        assert_eq!(chords(&Transposer::new(2).transpose(song)), vec!["Eb", "Cm"]);
+    }
+
+    #[test]
+    fn transpose_noop() {
+        use super::*;
+
+        // Neither the chords nor the key are respelled
+        for semitones in [0, 12, -24] {
+            let song = Song::from_str("{key: C#}
+[C#]A [A#m]B").expect("Failed to parse song");
+            let song = Transposer::new(semitones).transpose(song);
+            assert_eq!(chords(&song), vec!["C#", "A#m"]);
+            assert_eq!(song.metadata.key.as_deref(), Some("C#"));
+        }
+    }
+
+    #[test]
+    fn transpose_to() {
+        use super::*;
+
+        let mut song = Song::from_str("{key: Em}\n[Em]A [B7]B [C]C").expect("Failed to parse song");
+        song.transpose_to(Key::from_str("D").unwrap());
+        assert_eq!(chords(&song), vec!["Dm", "A7", "Bb"]);
+        assert_eq!(song.metadata.key.as_deref(), Some("Dm"));
+
+        let mut song = Song::from_str("[G]A [D/F#]B [Em]C").expect("Failed to parse song");
+        song.transpose_to(Key::from_str("Bb").unwrap());
+        assert_eq!(chords(&song), vec!["Bb", "F/A", "Gm"]);
+        assert_eq!(song.metadata.key.as_deref(), Some("Bb"));
+
+        let mut song = Song::from_str("{key: A}\n[A]A").expect("Failed to parse song");
+        let a_to_db = Transposer::to_key(Key::from_str("A").unwrap(), Key::from_str("Db").unwrap());
+        a_to_db.apply_transpose(&mut song);
+        assert_eq!(chords(&song), vec!["Db"]);
+        assert_eq!(song.metadata.key.as_deref(), Some("Db"));
+    }
+
+    #[test]
+    fn transposed() {
+        use super::*;
+
+        let song = Song::from_str(
+            "{key: G}\n{capo: 3}\n[G]Verse [D/F#]one\n\n{transpose: 2}\n[G]Verse [Em]two\n\n{transpose: 0}\n[C]End",
+        )
+        .expect("Failed to parse song");
+
+        let played = song.transposed(Pitch::Played);
+        assert_eq!(chords(&played), vec!["G", "D/F#", "A", "F#m", "C"]);
+        assert_eq!(played.song.len(), 3);
+        assert_eq!(played.metadata.key.as_deref(), Some("G"));
+        assert_eq!(played.metadata.capo, 3);
+
+        let concert = song.transposed(Pitch::Concert);
+        assert_eq!(chords(&concert), vec!["Bb", "F/A", "C", "Am", "Eb"]);
+        assert_eq!(concert.metadata.key.as_deref(), Some("Bb"));
+        assert_eq!(concert.metadata.capo, 0);
+    }
+
+    #[test]
+    fn transpose_german() {
+        use super::*;
+        use crate::chords::NoteNames;
+
+        // `B` is B flat, and the key is written back in German
+        let mut song = Song::parse_with("{key: B}\n[B]x [F]y", NoteNames::German).expect("Failed to parse song");
+        assert_eq!(song.key(), Some(Key::from_str("Bb").unwrap()));
+        song.transpose_to(Key::from_str("C").unwrap());
+        assert_eq!(chords(&song), vec!["C", "G"]);
+        assert_eq!(song.metadata.key.as_deref(), Some("C"));
+
+        let song = Song::parse_with("{key: B}\n{capo: 1}\n[B]x", NoteNames::German).expect("Failed to parse song");
+        let concert = song.transposed(Pitch::Concert);
+        assert_eq!(chords(&concert), vec!["B"]);
+        assert_eq!(concert.metadata.key.as_deref(), Some("H"));
+        assert_eq!(concert.key(), Some(Key::from_str("B").unwrap()));
+    }
+
+    #[test]
+    fn transposed_out_of_range() {
+        use super::*;
+
+        let song = Song::from_str("{key: C}\n{transpose: -20}\n[C]A [G]B").expect("Failed to parse song");
+        let played = song.transposed(Pitch::Played);
+        assert_eq!(chords(&played), vec!["E", "B"]);
+        assert_eq!(played.metadata.key.as_deref(), Some("E"));
+
+        let song = Song::from_str("{capo: 100}\n{transpose: 100}\n[C]A").expect("Failed to parse song");
+        assert_eq!(chords(&song.transposed(Pitch::Concert)), vec!["G#"]);
+        assert_eq!(chords(&song.transposed(Pitch::Played)), vec!["E"]);
+
+        let song = Song::from_str("{capo: 200}\n[C]A").expect("Failed to parse song");
+        assert_eq!(chords(&song.transposed(Pitch::Concert)), vec!["G#"]);
+
+        let song = Song::from_str("{capo: 12}\n{transpose: -12}\n[C#]A").expect("Failed to parse song");
+        assert_eq!(chords(&song.transposed(Pitch::Concert)), vec!["C#"]);
+    }
+
+    #[test]
+    fn transpose_grid() {
+        use super::*;
+        use crate::grid::GridCell;
+        use std::str::FromStr;
+        let song = Song::from_str("{start_of_grid}\n| C~G . | Am . |\n{end_of_grid}")
+            .expect("Failed to parse song");
+        let song2 = Transposer::new(2).transpose(song);
+
+        let grid = match song2.iter().next().unwrap() {
+            Section::Grid(grid) => grid,
+            s => panic!("Expected a grid, found {:?}", s),
+        };
+        assert_eq!(
+            grid.rows[0].measures[0].cells[0],
+            GridCell::Split(vec![Chord::from_str("D").unwrap(), Chord::from_str("A").unwrap()])
+        );
+        assert_eq!(
+            grid.rows[0].measures[1].cells[0],
+            GridCell::Chord(Chord::from_str("Bm").unwrap())
+        );
+    }
 }
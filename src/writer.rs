@@ -0,0 +1,664 @@
+//! # Writer module
+//! This module converts a `Song` back to ChordPro text.
+//!
+//! Wrap a reference to the song in `ChordPro`, which implements the
+//! `std::fmt::Display` trait, or call `Song::to_chordpro()`.
+//!
+//! The output can be parsed again with `Song::from_str`, giving back the
+//! same song:
+//! ```
+//! # use chordpro::Song;
+//! # use std::str::FromStr;
+//! let song = Song::from_str("{title: Song Title}\n[G]Some lyrics").unwrap();
+//! let text = song.to_chordpro();
+//!
+//! assert_eq!(Song::from_str(&text).unwrap(), song);
+//! ```
+
+use crate::chords::NoteNames;
+use crate::format::{GermanKey, Notated, Notation};
+use crate::grid::NotatedRow;
+use crate::metadata::Metadata;
+use crate::song::{Chunk, Environment, Line, Paragraph, Section, Song};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Wrapper to format a song as ChordPro text, with the note names it was
+/// parsed with
+pub struct ChordPro<'a>(pub &'a Song);
+
+impl<'a> Display for ChordPro<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let notation = match self.0.metadata.names {
+            NoteNames::Latin => Notation::Latin,
+            NoteNames::German => Notation::German,
+            NoteNames::Auto | NoteNames::Letters => Notation::Standard,
+        };
+        write!(f, "{}", Writer(self.0, notation))
+    }
+}
+
+/// Writes a song with its chords in a `Notation`
+struct Writer<'a>(&'a Song, Notation);
+
+impl<'a> Display for Writer<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let Writer(song, notation) = *self;
+        let mut separate =
+            write_metadata(f, &song.metadata, written_key(song, notation).as_deref())?;
+
+        for definition in song.definitions.iter() {
+            let directive = if definition.show { "chord" } else { "define" };
+            let condition = definition
+                .condition
+                .as_ref()
+                .map(|c| c.to_string())
+                .unwrap_or_default();
+            writeln!(f, "{{{}{}: {}}}", directive, condition, definition)?;
+            separate = true;
+        }
+
+        for section in song.iter() {
+            if separate {
+                writeln!(f)?;
+            }
+            write_section(f, section, "", notation)?;
+            separate = true;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> AsRef<Song> for ChordPro<'a> {
+    fn as_ref(&self) -> &Song {
+        self.0
+    }
+}
+
+/// The `{key}` of the song as it is written with its chords in `notation`.
+/// German names are only read in German songs, so the key is respelled
+/// when the chords change from or to them.
+fn written_key(song: &Song, notation: Notation) -> Option<String> {
+    let german = song.metadata.names == NoteNames::German;
+
+    match (song.key(), notation) {
+        (Some(key), Notation::German) if !german => Some(GermanKey(key).to_string()),
+        (Some(key), notation) if german && notation != Notation::German => Some(key.to_string()),
+        _ => song.metadata.key.clone(),
+    }
+}
+
+/// Writes the metadata directives, with `key` as the `{key}`. Returns
+/// whether anything was written.
+fn write_metadata(
+    f: &mut Formatter,
+    metadata: &Metadata,
+    key: Option<&str>,
+) -> Result<bool, fmt::Error> {
+    let mut written = false;
+    let mut directive = |f: &mut Formatter, name: &str, value: &dyn Display| {
+        written = true;
+        writeln!(f, "{{{}: {}}}", name, value)
+    };
+
+    if !metadata.title.is_empty() {
+        directive(f, "title", &metadata.title)?;
+    }
+
+    for (name, values) in [
+        ("subtitle", &metadata.subtitles),
+        ("artist", &metadata.artists),
+        ("composer", &metadata.composers),
+        ("lyricist", &metadata.lyricists),
+    ] {
+        for value in values {
+            directive(f, name, value)?;
+        }
+    }
+
+    for (name, value) in [
+        ("copyright", &metadata.copyright),
+        ("album", &metadata.album),
+    ] {
+        if let Some(value) = value {
+            directive(f, name, value)?;
+        }
+    }
+
+    if let Some(year) = metadata.year {
+        directive(f, "year", &year)?;
+    }
+
+    if let Some(key) = key {
+        directive(f, "key", &key)?;
+    }
+
+    if let Some(time) = &metadata.time {
+        directive(f, "time", time)?;
+    }
+
+    if let Some(tempo) = metadata.tempo {
+        directive(f, "tempo", &tempo)?;
+    }
+
+    if let Some(duration) = &metadata.duration {
+        directive(f, "duration", duration)?;
+    }
+
+    if metadata.capo != 0 {
+        directive(f, "capo", &metadata.capo)?;
+    }
+
+    for (name, values) in metadata.meta.iter() {
+        for value in values {
+            directive(f, "meta", &format_args!("{} {}", name, value))?;
+        }
+    }
+
+    for (condition, name, value) in metadata.conditional.iter() {
+        directive(
+            f,
+            &format!("meta{}", condition),
+            &format_args!("{} {}", name, value),
+        )?;
+    }
+
+    Ok(written)
+}
+
+/// Writes a section. `condition` is the selector suffix of its directives,
+/// or an empty string.
+fn write_section(
+    f: &mut Formatter,
+    section: &Section,
+    condition: &str,
+    notation: Notation,
+) -> fmt::Result {
+    match section {
+        Section::Chorus(e) => write_environment(
+            f,
+            e,
+            &format!("soc{}", condition),
+            &format!("eoc{}", condition),
+            notation,
+        ),
+        // A verse with a condition needs an explicit environment
+        Section::Verse(p) if !condition.is_empty() => write_environment(
+            f,
+            &p.clone().into(),
+            &format!("start_of_verse{}", condition),
+            &format!("end_of_verse{}", condition),
+            notation,
+        ),
+        Section::Verse(p) => write_paragraph(f, p, notation),
+        Section::Comment(l) => {
+            write!(f, "{{comment{}: ", condition)?;
+            write_line(f, l, notation)?;
+            writeln!(f, "}}")
+        }
+        Section::Environment(kind, e) => write_environment(
+            f,
+            e,
+            &format!("start_of_{}{}", kind.name(), condition),
+            &format!("end_of_{}{}", kind.name(), condition),
+            notation,
+        ),
+        Section::Tab(tab) => {
+            write_start(f, &format!("start_of_tab{}", condition), &tab.label)?;
+            for line in tab.lines.iter() {
+                writeln!(f, "{}", line)?;
+            }
+            writeln!(f, "{{end_of_tab{}}}", condition)
+        }
+        Section::Grid(grid) => {
+            match (&grid.label, &grid.shape) {
+                (Some(label), Some(shape)) => writeln!(
+                    f,
+                    "{{start_of_grid{}: shape=\"{}\" label=\"{}\"}}",
+                    condition, shape, label
+                )?,
+                (Some(label), None) => {
+                    writeln!(f, "{{start_of_grid{}: label=\"{}\"}}", condition, label)?
+                }
+                (None, Some(shape)) => writeln!(f, "{{start_of_grid{}: {}}}", condition, shape)?,
+                (None, None) => writeln!(f, "{{start_of_grid{}}}", condition)?,
+            }
+            for row in grid.rows.iter() {
+                writeln!(f, "{}", NotatedRow(row, notation))?;
+            }
+            writeln!(f, "{{end_of_grid{}}}", condition)
+        }
+        Section::ChorusRef(label) => write_start(f, &format!("chorus{}", condition), label),
+        Section::Transpose(semitones) => writeln!(f, "{{transpose{}: {}}}", condition, semitones),
+        Section::Conditional(c, section) => write_section(f, section, &c.to_string(), notation),
+    }
+}
+
+fn write_environment(
+    f: &mut Formatter,
+    environment: &Environment,
+    start: &str,
+    end: &str,
+    notation: Notation,
+) -> fmt::Result {
+    write_start(f, start, &environment.label)?;
+    write_paragraph(f, &environment.content, notation)?;
+    writeln!(f, "{{{}}}", end)
+}
+
+/// Writes the directive that starts an environment, with its label
+fn write_start(f: &mut Formatter, start: &str, label: &Option<String>) -> fmt::Result {
+    match label {
+        Some(label) => writeln!(f, "{{{}: {}}}", start, label),
+        None => writeln!(f, "{{{}}}", start),
+    }
+}
+
+fn write_paragraph(f: &mut Formatter, paragraph: &Paragraph, notation: Notation) -> fmt::Result {
+    for line in paragraph.0.iter() {
+        write_line(f, line, notation)?;
+        writeln!(f)?;
+    }
+
+    Ok(())
+}
+
+fn write_line(f: &mut Formatter, line: &Line, notation: Notation) -> fmt::Result {
+    for chunk in line.iter() {
+        match chunk {
+            Chunk::Lyrics(s) => write!(f, "{}", s)?,
+            Chunk::Chord(c) => write!(f, "[{}]", Notated(c, notation))?,
+        }
+    }
+
+    Ok(())
+}
+
+impl Song {
+    /// Writes the song as ChordPro text, with the note names it was parsed
+    /// with
+    pub fn to_chordpro(&self) -> String {
+        ChordPro(self).to_string()
+    }
+
+    /// Writes the song as ChordPro text, with its chords in `notation`.
+    ///
+    /// Songs written in Nashville numbers or Roman numerals can be parsed
+    /// again if their `{key}` is the key of the notation. The `{key}` is
+    /// respelled when the chords change from or to German names.
+    pub fn to_chordpro_in(&self, notation: Notation) -> String {
+        Writer(self, notation).to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::format::test::chord;
+    use crate::metadata::Inserted;
+    use crate::{
+        BarLine, Chord, ChordDefinition, ChordPro, Chunk, Condition, Environment, EnvironmentKind,
+        Grid, GridCell, GridRow, GridShape, Key, Line, Measure, Metadata, Notation, Note,
+        NoteNames, Paragraph, Section, Song, Tab,
+    };
+    use proptest::prelude::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn write_song() {
+        let mut metadata = Metadata::default();
+        metadata.insert("title", "Song Title");
+        metadata.insert("artist", "The Artist");
+        metadata.insert("capo", "2");
+        metadata.insert("label", "Records");
+
+        let song = Song {
+            metadata,
+            definitions: vec![ChordDefinition {
+                name: "Am".to_string(),
+                chord: Some(Chord::minor(Note::A)),
+                base_fret: Some(1),
+                frets: vec![None, Some(0), Some(2), Some(2), Some(1), Some(0)],
+                ..Default::default()
+            }],
+            song: vec![
+                Section::Verse(Paragraph(vec![Line(vec![
+                    Chunk::Lyrics("This is a ".to_string()),
+                    Chunk::Chord(Chord::major(Note::G)),
+                    Chunk::Lyrics("chord".to_string()),
+                ])])),
+                Section::Chorus(
+                    Paragraph(vec![Line(vec![
+                        Chunk::Chord(Chord::minor(Note::E)),
+                        Chunk::Lyrics("The chorus".to_string()),
+                    ])])
+                    .into(),
+                ),
+                Section::Environment(
+                    EnvironmentKind::Bridge,
+                    Environment {
+                        label: Some("Bridge 1".to_string()),
+                        content: Paragraph(vec![Line(vec![Chunk::Lyrics(
+                            "The bridge".to_string(),
+                        )])]),
+                    },
+                ),
+                Section::Comment(Line(vec![Chunk::Lyrics("Repeat".to_string())])),
+            ],
+        };
+
+        assert_eq!(
+            ChordPro(&song).to_string(),
+            "{title: Song Title}\n\
+             {artist: The Artist}\n\
+             {capo: 2}\n\
+             {meta: label Records}\n\
+             {define: Am base-fret 1 frets x 0 2 2 1 0}\n\
+             \n\
+             This is a [G]chord\n\
+             \n\
+             {soc}\n\
+             [Em]The chorus\n\
+             {eoc}\n\
+             \n\
+             {start_of_bridge: Bridge 1}\n\
+             The bridge\n\
+             {end_of_bridge}\n\
+             \n\
+             {comment: Repeat}\n"
+        );
+    }
+
+    #[test]
+    fn write_empty_song() {
+        assert_eq!(Song::default().to_chordpro(), "");
+    }
+
+    #[test]
+    fn write_relative() {
+        let song = Song::from_str(
+            "{key: G}\n[G]One [C]two [Em]three [D/F#]four\n\n{sog}\n| G . Bm . |\n{eog}",
+        )
+        .unwrap();
+        let key = Key::from_str("G").unwrap();
+
+        let nashville = song.to_chordpro_in(Notation::Nashville(key));
+        assert_eq!(nashville, "{key: G}\n\n[1]One [4]two [6m]three [5/7]four\n\n{start_of_grid}\n| 1 . 3m . |\n{end_of_grid}\n");
+        assert_eq!(Song::from_str(&nashville).unwrap(), song);
+
+        let roman = song.to_chordpro_in(Notation::Roman(key));
+        assert!(roman.contains("[I]One [IV]two [vi]three [V/vii]four"));
+        assert_eq!(Song::from_str(&roman).unwrap(), song);
+    }
+
+    #[test]
+    fn write_german() {
+        let song = Song::parse_with(
+            "{key: B}\n[H7]Hallo [B]Welt\n\n{sog}\n| B . H . |\n{eog}",
+            NoteNames::German,
+        )
+        .unwrap();
+
+        let written = song.to_chordpro();
+        assert_eq!(
+            written,
+            "{key: B}\n\n[H7]Hallo [B]Welt\n\n{start_of_grid}\n| B . H . |\n{end_of_grid}\n"
+        );
+        assert_eq!(Song::parse_with(&written, NoteNames::German).unwrap(), song);
+
+        // With letter names, the key is written with them too
+        let letters = song.to_chordpro_in(Notation::Standard);
+        assert!(letters.starts_with("{key: Bb}\n\n[B7]Hallo [Bb]Welt"));
+        assert_eq!(Song::from_str(&letters).unwrap().key(), song.key());
+
+        let song = Song::from_str("{key: Bb}\n[Bb]One").unwrap();
+        let german = song.to_chordpro_in(Notation::German);
+        assert_eq!(german, "{key: B}\n\n[B]One\n");
+        assert_eq!(
+            Song::parse_with(&german, NoteNames::German).unwrap().key(),
+            song.key()
+        );
+    }
+
+    fn text() -> impl Strategy<Value = String> {
+        "[A-Za-z,.'!?][A-Za-z0-9 ,.'!?]{0,15}"
+    }
+
+    fn line() -> impl Strategy<Value = Line> {
+        let chunk = prop_oneof![
+            text().prop_map(Chunk::Lyrics),
+            chord().prop_map(Chunk::Chord),
+        ];
+
+        proptest::collection::vec(chunk, 1..6).prop_map(|chunks| {
+            // Consecutive lyrics are read back as a single chunk
+            let mut line = Line::default();
+            for chunk in chunks {
+                match (line.0.last_mut(), chunk) {
+                    (Some(Chunk::Lyrics(last)), Chunk::Lyrics(s)) => last.push_str(&s),
+                    (_, chunk) => line.0.push(chunk),
+                }
+            }
+            line
+        })
+    }
+
+    fn paragraph() -> impl Strategy<Value = Paragraph> {
+        proptest::collection::vec(line(), 1..4).prop_map(Paragraph)
+    }
+
+    fn environment() -> impl Strategy<Value = Environment> {
+        let label = proptest::option::of(text().prop_map(|s| s.trim_end().to_owned()));
+
+        (label, paragraph()).prop_map(|(label, content)| Environment { label, content })
+    }
+
+    fn environment_kind() -> impl Strategy<Value = EnvironmentKind> {
+        prop_oneof![
+            Just(EnvironmentKind::Verse),
+            Just(EnvironmentKind::Bridge),
+            "[a-z][a-z_]{0,8}"
+                .prop_filter("Known environment", |name| {
+                    !["chorus", "verse", "bridge"].contains(&name.as_str())
+                })
+                .prop_map(EnvironmentKind::Custom),
+        ]
+    }
+
+    fn tab() -> impl Strategy<Value = Tab> {
+        let tab_line = "[ -~]{0,20}".prop_filter("End of tab", |line| {
+            let line = line.trim_start();
+            !line.starts_with("{eot}") && !line.starts_with("{end_of_tab}")
+        });
+        let label = proptest::option::of(text().prop_map(|s| s.trim_end().to_owned()));
+
+        (label, proptest::collection::vec(tab_line, 0..5))
+            .prop_map(|(label, lines)| Tab { label, lines })
+    }
+
+    fn grid() -> impl Strategy<Value = Grid> {
+        let bar = prop_oneof![
+            Just(BarLine::Single),
+            Just(BarLine::Double),
+            Just(BarLine::End),
+            Just(BarLine::RepeatStart),
+            Just(BarLine::DoubleRepeatStart),
+            Just(BarLine::RepeatEnd),
+            Just(BarLine::DoubleRepeatEnd),
+            Just(BarLine::RepeatBoth),
+            (1..4u8).prop_map(BarLine::Volta),
+            (2..4u8).prop_map(BarLine::RepeatEndVolta),
+        ];
+        let cell = prop_oneof![
+            chord().prop_map(GridCell::Chord),
+            proptest::collection::vec(chord(), 2..4).prop_map(GridCell::Split),
+            Just(GridCell::Continue),
+            Just(GridCell::RepeatMeasure),
+            Just(GridCell::RepeatTwoMeasures),
+        ];
+        let measure = (proptest::collection::vec(cell, 0..5), bar.clone())
+            .prop_map(|(cells, end)| Measure { cells, end });
+        let words = proptest::option::of("[A-Za-z]{1,6}( [A-Za-z0-9]{1,6})?");
+        let row = (
+            words.clone(),
+            bar,
+            proptest::collection::vec(measure, 0..4),
+            words,
+        )
+            .prop_map(|(margin, start, measures, comment)| GridRow {
+                margin,
+                start: Some(start),
+                measures,
+                comment,
+            });
+        let shape =
+            (0..3u8, 1..9u8, 1..5u8, 0..3u8).prop_map(|(margin, measures, beats, comment)| {
+                GridShape {
+                    margin,
+                    measures,
+                    beats,
+                    comment,
+                }
+            });
+        let label = proptest::option::of(text().prop_map(|s| s.trim_end().to_owned()));
+
+        (
+            label,
+            proptest::option::of(shape),
+            proptest::collection::vec(row, 0..4),
+        )
+            .prop_map(|(label, shape, rows)| Grid { label, shape, rows })
+    }
+
+    fn condition() -> impl Strategy<Value = Condition> {
+        ("[a-z][a-z_]{0,8}", any::<bool>()).prop_map(|(name, negated)| Condition { name, negated })
+    }
+
+    fn section() -> impl Strategy<Value = Section> {
+        // Verses are written with an environment when they have a condition,
+        // so only explicit environments get one
+        let unconditional = prop_oneof![
+            paragraph().prop_map(Section::Verse),
+            environment().prop_map(Section::Chorus),
+            line().prop_map(Section::Comment),
+            (environment_kind(), environment()).prop_map(|(kind, e)| Section::Environment(kind, e)),
+            tab().prop_map(Section::Tab),
+            grid().prop_map(Section::Grid),
+            proptest::option::of(text().prop_map(|s| s.trim_end().to_owned()))
+                .prop_map(Section::ChorusRef),
+            (-11..12i8).prop_map(Section::Transpose),
+        ];
+
+        prop_oneof![
+            4 => unconditional.clone(),
+            1 => (condition(), unconditional)
+                .prop_filter("Conditional verse", |(_, s)| !matches!(s, Section::Verse(_)))
+                .prop_map(|(c, s)| Section::Conditional(c, Box::new(s))),
+        ]
+    }
+
+    fn metadata() -> impl Strategy<Value = Metadata> {
+        let values = || proptest::collection::vec(text(), 0..3);
+        let meta_name = "[a-z][a-z_]{0,8}".prop_filter("Standard metadata name", |name| {
+            Metadata::default().insert_standard(name, "") == Inserted::Unknown
+        });
+
+        (
+            (
+                prop_oneof![Just(String::new()), text()],
+                values(),
+                values(),
+                values(),
+                values(),
+                proptest::option::of(text()),
+                proptest::option::of(text()),
+            ),
+            (
+                proptest::option::of(any::<u16>()),
+                proptest::option::of(text()),
+                proptest::option::of(text()),
+                proptest::option::of(any::<u16>()),
+                proptest::option::of(text()),
+                any::<u8>(),
+                proptest::collection::btree_map(meta_name.clone(), values(), 0..3),
+                proptest::collection::vec((condition(), meta_name, text()), 0..3),
+            ),
+        )
+            .prop_map(
+                |(
+                    (title, subtitles, artists, composers, lyricists, copyright, album),
+                    (year, key, time, tempo, duration, capo, meta, conditional),
+                )| Metadata {
+                    title,
+                    subtitles,
+                    artists,
+                    composers,
+                    lyricists,
+                    copyright,
+                    album,
+                    year,
+                    key,
+                    time,
+                    tempo,
+                    duration,
+                    capo,
+                    meta: meta.into_iter().filter(|(_, v)| !v.is_empty()).collect(),
+                    names: NoteNames::Auto,
+                    conditional,
+                },
+            )
+    }
+
+    fn definition() -> impl Strategy<Value = ChordDefinition> {
+        let positions = || proptest::collection::vec(proptest::option::of(0..6u8), 0..7);
+
+        (
+            chord(),
+            proptest::option::of(1..12u8),
+            positions(),
+            positions(),
+            proptest::collection::vec(0..24u8, 0..5),
+            any::<bool>(),
+            proptest::option::of(condition()),
+        )
+            .prop_map(
+                |(chord, base_fret, frets, fingers, keys, show, condition)| ChordDefinition {
+                    name: chord.to_string(),
+                    chord: Some(chord),
+                    base_fret,
+                    frets,
+                    fingers,
+                    keys,
+                    show,
+                    condition,
+                },
+            )
+    }
+
+    fn song() -> impl Strategy<Value = Song> {
+        (
+            metadata(),
+            proptest::collection::vec(definition(), 0..3),
+            proptest::collection::vec(section(), 0..6),
+        )
+            .prop_map(|(metadata, definitions, song)| Song {
+                metadata,
+                definitions,
+                song,
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn song_roundtrip(s in song()) {
+            let written = s.to_chordpro();
+            let parsed = Song::from_str(&written);
+
+            prop_assert!(parsed.is_ok(), "Failed to parse:\n{}", written);
+            prop_assert_eq!(parsed.unwrap(), s.clone());
+
+            let latin = s.to_chordpro_in(Notation::Latin);
+            prop_assert_eq!(Song::from_str(&latin).unwrap(), s);
+        }
+    }
+}